@@ -0,0 +1,111 @@
+//! Per-step overhead of generators, compared against a hand-written iterator.
+//!
+//! Run with `cargo bench`. There are no dependencies, so this just times
+//! each case a few times and prints the fastest run.
+
+use std::{
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+use dedenne::{batch::BatchGenerator, *};
+
+const ITEMS: u64 = 1_000_000;
+const RUNS: u32 = 7;
+
+/// What every case computes, so the optimizer can't skip anything.
+const EXPECTED: u64 = ITEMS * (ITEMS - 1) / 2;
+
+struct Counter {
+  next: u64,
+  end: u64,
+}
+
+impl Iterator for Counter {
+  type Item = u64;
+
+  fn next(&mut self) -> Option<u64> {
+    if self.next < self.end {
+      let out = self.next;
+      self.next += 1;
+      Some(out)
+    } else {
+      None
+    }
+  }
+}
+
+fn hand_written() -> u64 {
+  Counter {
+    next: 0,
+    end: black_box(ITEMS),
+  }
+  .map(black_box)
+  .sum()
+}
+
+fn resume_iter() -> u64 {
+  let (generator, first) = StartedGenerator::run(|y| async move {
+    for i in 1..black_box(ITEMS) {
+      y.ield(i).await;
+    }
+  });
+  let first = match first {
+    GeneratorResponse::Yielding(first) => first,
+    GeneratorResponse::Done(()) => unreachable!(),
+  };
+  first + generator.iter().map(black_box).sum::<u64>()
+}
+
+fn query_round_trip() -> u64 {
+  let (mut generator, _) = StartedGenerator::run(|y| async move {
+    let mut total = 0;
+    for i in 0..black_box(ITEMS) {
+      total += y.ield(i).await;
+    }
+    total
+  });
+  let mut next = 0;
+  loop {
+    match generator.query(next) {
+      GeneratorResponse::Yielding(i) => next = i,
+      GeneratorResponse::Done(total) => return total,
+    }
+  }
+}
+
+fn batched() -> u64 {
+  let (mut generator, mut resp) = BatchGenerator::run(|y| async move {
+    for i in 0..black_box(ITEMS) {
+      y.emit(i);
+      if y.pending_len() == 1024 {
+        y.flush().await;
+      }
+    }
+  });
+  let mut total = 0;
+  while let GeneratorResponse::Yielding(batch) = resp {
+    total += batch.into_iter().map(black_box).sum::<u64>();
+    resp = generator.resume();
+  }
+  total
+}
+
+fn bench(name: &str, f: fn() -> u64) {
+  let mut best = Duration::MAX;
+  for _ in 0..RUNS {
+    let start = Instant::now();
+    let out = black_box(f());
+    best = best.min(start.elapsed());
+    assert_eq!(out, EXPECTED, "{} computed the wrong thing", name);
+  }
+  let per_item = best.as_secs_f64() * 1e9 / ITEMS as f64;
+  println!("{:<20} {:>8.2} ns/item", name, per_item);
+}
+
+fn main() {
+  bench("hand_written", hand_written);
+  bench("resume_iter", resume_iter);
+  bench("query_round_trip", query_round_trip);
+  bench("batched", batched);
+}
@@ -99,7 +99,7 @@ impl BazQuxxInstaller {
 
       if cleaned == "y" || cleaned == "yes" {
         return true;
-      } else if cleaned == "" || cleaned == "n" || cleaned == "no" {
+      } else if cleaned.is_empty() || cleaned == "n" || cleaned == "no" {
         return false;
       } else {
         println!("Didn't recognize {}", resp);
@@ -114,11 +114,7 @@ fn main() -> io::Result<()> {
     BazQuxxInstaller::start(y).await;
   });
   let mut stdout = io::stdout();
-  loop {
-    let msg = match output {
-      GeneratorResponse::Yielding(msg) => msg,
-      GeneratorResponse::Done(()) => break,
-    };
+  while let GeneratorResponse::Yielding(msg) = output {
     write!(&mut stdout, "{}", msg)?;
     stdout.flush()?;
 
@@ -0,0 +1,188 @@
+//! Yielding lots of small things cheaply.
+//!
+//! Every `y.ield` is a full suspend and resume. That's fine for most
+//! generators, but if you're producing millions of tiny items it adds up.
+//! Instead, the body of a [`BatchGenerator`] can [`emit`](Batcher::emit)
+//! items one at a time, which just pushes them onto a buffer, and then
+//! [`flush`](Batcher::flush) the buffer every so often,
+//! yielding everything in one go.
+//!
+//! On the outside, [`query_batch`](BatchGenerator::query_batch) drives the
+//! generator with several queries at once and hands back everything it
+//! yielded, all in one `Vec`.
+//!
+//! ```rust
+//! # use dedenne::{batch::BatchGenerator, GeneratorResponse};
+//! let (mut gen, resp) = BatchGenerator::run(|y| async move {
+//!   for chunk in 0..3 {
+//!     for i in 0..4 {
+//!       y.emit(chunk * 4 + i);
+//!     }
+//!     y.flush().await;
+//!   }
+//!   // This gets flushed for you when it returns
+//!   y.emit(12);
+//!   "done"
+//! });
+//! assert_eq!(resp, GeneratorResponse::Yielding(vec![0, 1, 2, 3]));
+//! let (items, done) = gen.query_batch([(), (), ()]);
+//! assert_eq!(items, (4..13).collect::<Vec<_>>());
+//! assert_eq!(done, Some("done"));
+//! ```
+
+use std::{cell::RefCell, fmt, future::Future, rc::Rc};
+
+use crate::{GeneratorResponse, StartedGenerator, YieldPoint, YieldWrapper};
+
+/// The type of `y` in a [`BatchGenerator`]'s body.
+pub struct Batcher<Q, T> {
+  y: YieldWrapper<Q, Vec<T>>,
+  /// Things `emit`ted but not `flush`ed yet
+  pending: Rc<RefCell<Vec<T>>>,
+}
+
+impl<Q, T> Batcher<Q, T> {
+  /// Put `item` in the buffer, without suspending.
+  ///
+  /// It gets yielded with everything else the next time you `flush`,
+  /// or when the generator returns.
+  pub fn emit(&self, item: T) {
+    self.pending.borrow_mut().push(item);
+  }
+
+  /// How many items have been `emit`ted since the last `flush`.
+  pub fn pending_len(&self) -> usize {
+    self.pending.borrow().len()
+  }
+
+  /// Yield everything that's been `emit`ted so far (which may be nothing),
+  /// and suspend until the next query, just like `y.ield`.
+  #[track_caller]
+  pub fn flush(&self) -> impl Future<Output = Q> {
+    let batch = self.pending.take();
+    self.y.ield_at(YieldPoint::here(Some("flush")), batch)
+  }
+}
+
+/// A generator that yields its items in batches; see the [module](self)
+/// docs.
+pub struct BatchGenerator<T, R, Q = ()> {
+  inner: StartedGenerator<Vec<T>, R, Q>,
+  /// Shared with the body's `Batcher`, so the leftovers are still here
+  /// once it's returned
+  pending: Rc<RefCell<Vec<T>>>,
+  /// The body's returned, but we yielded its leftovers first
+  returned: Option<R>,
+}
+
+impl<T, R, Q> BatchGenerator<T, R, Q> {
+  /// Create and start a batch generator.
+  pub fn run<F, Fut>(f: F) -> (Self, GeneratorResponse<Vec<T>, R>)
+  where
+    F: FnOnce(Batcher<Q, T>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    T: 'static,
+    Q: 'static,
+  {
+    let pending = Rc::new(RefCell::new(Vec::new()));
+    let batcher_pending = Rc::clone(&pending);
+    let (inner, resp) = StartedGenerator::run(move |y| {
+      f(Batcher {
+        y,
+        pending: batcher_pending,
+      })
+    });
+    let mut gen = Self {
+      inner,
+      pending,
+      returned: None,
+    };
+    let resp = gen.settle(resp);
+    (gen, resp)
+  }
+
+  /// Query the generator, and get back the next batch.
+  ///
+  /// If the body returns with things still `emit`ted, those are yielded
+  /// as one last batch, and you get the `R` from the query after.
+  pub fn query(&mut self, query: Q) -> GeneratorResponse<Vec<T>, R> {
+    if let Some(done) = self.returned.take() {
+      return GeneratorResponse::Done(done);
+    }
+    let resp = self.inner.query(query);
+    self.settle(resp)
+  }
+
+  /// Query the generator with each of `queries` in turn,
+  /// and collect everything it yields into one `Vec`.
+  ///
+  /// If the generator returns partway through, you get its `R` too, along
+  /// with anything it emitted but didn't flush, and the rest of the queries
+  /// aren't used. (Pass `&mut iter` if you want to keep them.)
+  pub fn query_batch<I>(&mut self, queries: I) -> (Vec<T>, Option<R>)
+  where
+    I: IntoIterator<Item = Q>,
+  {
+    let mut out = Vec::new();
+    if let Some(done) = self.returned.take() {
+      return (out, Some(done));
+    }
+    for query in queries {
+      match self.inner.query(query) {
+        GeneratorResponse::Yielding(batch) => {
+          if out.is_empty() {
+            out = batch;
+          } else {
+            out.extend(batch);
+          }
+        }
+        GeneratorResponse::Done(done) => {
+          out.append(&mut self.pending.borrow_mut());
+          return (out, Some(done));
+        }
+      }
+    }
+    (out, None)
+  }
+
+  /// Whether the generator has returned, and its `R` has been handed out.
+  pub fn is_finished(&self) -> bool {
+    self.inner.is_finished() && self.returned.is_none()
+  }
+
+  /// See [`StartedGenerator::suspended_at`].
+  pub fn suspended_at(&self) -> Option<YieldPoint> {
+    self.inner.suspended_at()
+  }
+
+  /// Hold on to the `R` if there are leftovers to yield first.
+  fn settle(
+    &mut self,
+    resp: GeneratorResponse<Vec<T>, R>,
+  ) -> GeneratorResponse<Vec<T>, R> {
+    match resp {
+      GeneratorResponse::Done(done) if !self.pending.borrow().is_empty() => {
+        self.returned = Some(done);
+        GeneratorResponse::Yielding(self.pending.take())
+      }
+      resp => resp,
+    }
+  }
+}
+
+impl<T, R> BatchGenerator<T, R, ()> {
+  /// Convenience function for generators that take `()` as their query.
+  pub fn resume(&mut self) -> GeneratorResponse<Vec<T>, R> {
+    self.query(())
+  }
+}
+
+impl<T, R, Q> fmt::Debug for BatchGenerator<T, R, Q> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("BatchGenerator")
+      .field("pending", &self.pending.borrow().len())
+      .field("finished", &self.is_finished())
+      .field("suspended_at", &self.suspended_at())
+      .finish_non_exhaustive()
+  }
+}
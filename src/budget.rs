@@ -0,0 +1,85 @@
+//! Cooperative time-slicing.
+//!
+//! Long computations (pathfinding, world generation, etc) can sprinkle
+//! `y.checkpoint(..).await` through their hot loops instead of hand-placing
+//! `y.ield`s. Then drive the generator with
+//! [`query_within`](crate::StartedGenerator::query_within) and a [`Budget`];
+//! the checkpoints only actually suspend once the budget is used up.
+
+use std::time::{Duration, Instant};
+
+/// How much work a generator is allowed to do before a
+/// [`checkpoint`](crate::YieldWrapper::checkpoint) suspends it.
+///
+/// If both a time and an iteration limit are set, the generator suspends
+/// as soon as either runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Budget {
+  iterations: Option<u32>,
+  time: Option<Duration>,
+}
+
+impl Budget {
+  /// A budget that never runs out. Checkpoints will never suspend,
+  /// so the generator runs until it `y.ield`s or returns.
+  pub fn unlimited() -> Self {
+    Self::default()
+  }
+
+  /// Suspend on the `n`th checkpoint.
+  pub fn iterations(n: u32) -> Self {
+    Self::unlimited().with_iterations(n)
+  }
+
+  /// Suspend on the first checkpoint after `time` has elapsed.
+  pub fn time(time: Duration) -> Self {
+    Self::unlimited().with_time(time)
+  }
+
+  pub fn with_iterations(self, n: u32) -> Self {
+    Self {
+      iterations: Some(n),
+      ..self
+    }
+  }
+
+  pub fn with_time(self, time: Duration) -> Self {
+    Self {
+      time: Some(time),
+      ..self
+    }
+  }
+
+  /// Start the clock on this budget.
+  pub(crate) fn begin(self) -> BudgetTracker {
+    BudgetTracker {
+      iterations_left: self.iterations,
+      deadline: self.time.map(|time| Instant::now() + time),
+    }
+  }
+}
+
+/// A [`Budget`] that is currently being spent.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BudgetTracker {
+  iterations_left: Option<u32>,
+  deadline: Option<Instant>,
+}
+
+impl BudgetTracker {
+  /// Spend one checkpoint's worth of budget.
+  /// Returns `true` if the budget is now used up.
+  pub(crate) fn spend(&mut self) -> bool {
+    let out_of_iterations = match &mut self.iterations_left {
+      Some(left) => {
+        *left = left.saturating_sub(1);
+        *left == 0
+      }
+      None => false,
+    };
+    let out_of_time = self
+      .deadline
+      .is_some_and(|deadline| Instant::now() >= deadline);
+    out_of_iterations || out_of_time
+  }
+}
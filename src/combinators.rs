@@ -0,0 +1,426 @@
+//! Combinators for adapting a generator's types and behavior.
+//!
+//! Each of these works by writing the wrapping async body you'd otherwise
+//! write by hand: the new generator drives the old one, one query at a time.
+//! So everything stays lazy, and the result is a normal generator you can
+//! `query`, `iter_over`, and so on.
+//!
+//! See [`GeneratorExt`] for combinators over a single generator,
+//! and the free functions in here for combining several.
+
+use std::future::Future;
+
+use crate::{
+  wrapper::{BoxedGenerator, Generator},
+  GeneratorResponse, StartedGenerator, YieldWrapper,
+};
+
+/// A started generator along with the response it last gave you,
+/// which is what [`StartedGenerator::run`] and friends return.
+pub type Running<Y, R, Q> =
+  (StartedGenerator<Y, R, Q>, GeneratorResponse<Y, R>);
+
+/// Extension methods for adapting generators.
+///
+/// This is implemented for:
+///
+/// * [`Running`] generators, so you can call it right on the return value of
+///   `StartedGenerator::run`. The adapted generator comes back already started
+///   too, with its first response adapted.
+/// * Unstarted [`wrapper::Generator`](Generator)s.
+///   The adapted generator is a [`BoxedGenerator`] that takes the same start
+///   argument. Adapting a `Generator` that's already started panics.
+pub trait GeneratorExt<Y: 'static, R: 'static, Q: 'static>: Sized {
+  /// What this turns into after being adapted.
+  type Adapted<Y2: 'static, R2: 'static, Q2: 'static>;
+
+  /// The building block for all the other combinators.
+  ///
+  /// `f` is the body of the new generator. It gets the `y` for the new
+  /// generator, and this one, already started.
+  fn adapt<Y2, R2, Q2, F, Fut>(self, f: F) -> Self::Adapted<Y2, R2, Q2>
+  where
+    F: FnOnce(YieldWrapper<Q2, Y2>, Running<Y, R, Q>) -> Fut + 'static,
+    Fut: Future<Output = R2> + 'static,
+    Y2: 'static,
+    R2: 'static,
+    Q2: 'static;
+
+  /// Transform everything this yields.
+  fn map_yield<Y2, M>(self, mut m: M) -> Self::Adapted<Y2, R, Q>
+  where
+    M: FnMut(Y) -> Y2 + 'static,
+    Y2: 'static,
+  {
+    self.adapt(move |y, (mut gen, mut resp)| async move {
+      loop {
+        match resp {
+          GeneratorResponse::Yielding(yielded) => {
+            let query = y.ield(m(yielded)).await;
+            resp = gen.query(query);
+          }
+          GeneratorResponse::Done(done) => return done,
+        }
+      }
+    })
+  }
+
+  /// Transform what this returns.
+  fn map_return<R2, M>(self, m: M) -> Self::Adapted<Y, R2, Q>
+  where
+    M: FnOnce(R) -> R2 + 'static,
+    R2: 'static,
+  {
+    self.adapt(move |y, running| async move { m(relay(&y, running).await) })
+  }
+
+  /// Accept a different query type, converting each one before
+  /// passing it along.
+  fn map_query<Q2, M>(self, mut m: M) -> Self::Adapted<Y, R, Q2>
+  where
+    M: FnMut(Q2) -> Q + 'static,
+    Q2: 'static,
+  {
+    self.adapt(move |y, (mut gen, mut resp)| async move {
+      loop {
+        match resp {
+          GeneratorResponse::Yielding(yielded) => {
+            let query = y.ield(yielded).await;
+            resp = gen.query(m(query));
+          }
+          GeneratorResponse::Done(done) => return done,
+        }
+      }
+    })
+  }
+
+  /// Only pass along yields that `pred` returns `true` for.
+  ///
+  /// The rest get answered with a clone of `default` without ever
+  /// leaving the adapted generator.
+  fn filter_yield<P>(self, mut pred: P, default: Q) -> Self::Adapted<Y, R, Q>
+  where
+    P: FnMut(&Y) -> bool + 'static,
+    Q: Clone,
+  {
+    self.adapt(move |y, (mut gen, mut resp)| async move {
+      loop {
+        match resp {
+          GeneratorResponse::Yielding(yielded) => {
+            let query = if pred(&yielded) {
+              y.ield(yielded).await
+            } else {
+              default.clone()
+            };
+            resp = gen.query(query);
+          }
+          GeneratorResponse::Done(done) => return done,
+        }
+      }
+    })
+  }
+
+  /// Pass along at most `n` yields.
+  ///
+  /// The adapted generator returns `Some` if this one returned within
+  /// `n` yields, and `None` if it got cut off.
+  fn take_yields(self, n: usize) -> Self::Adapted<Y, Option<R>, Q> {
+    self.adapt(move |y, (mut gen, mut resp)| async move {
+      for _ in 0..n {
+        match resp {
+          GeneratorResponse::Yielding(yielded) => {
+            let query = y.ield(yielded).await;
+            resp = gen.query(query);
+          }
+          GeneratorResponse::Done(done) => return Some(done),
+        }
+      }
+      match resp {
+        GeneratorResponse::Yielding(_) => None,
+        GeneratorResponse::Done(done) => Some(done),
+      }
+    })
+  }
+
+  /// Pipe everything this yields into `next`'s queries,
+  /// and yield whatever `next` yields.
+  ///
+  /// Like a Unix pipe, each stage can finish before the other:
+  ///
+  /// * If this one returns first, `next` gets queried with `None`
+  ///   (as in end-of-file) until it returns too.
+  /// * If `next` returns first, this one gets dropped, and the pipeline
+  ///   returns `None` for its result.
+  ///
+  /// Queries to the pipeline go to this generator.
+  fn then<Y2, R2>(
+    self,
+    next: Running<Y2, R2, Option<Y>>,
+  ) -> Self::Adapted<Y2, (Option<R>, R2), Q>
+  where
+    Y2: 'static,
+    R2: 'static,
+  {
+    self.adapt(move |y, (mut gen, mut resp)| async move {
+      let (mut next, mut next_resp) = next;
+      loop {
+        let yielded = match next_resp {
+          GeneratorResponse::Yielding(yielded) => yielded,
+          GeneratorResponse::Done(next_done) => {
+            let done = match resp {
+              GeneratorResponse::Yielding(_) => None,
+              GeneratorResponse::Done(done) => Some(done),
+            };
+            return (done, next_done);
+          }
+        };
+        let query = y.ield(yielded).await;
+        let piped = match resp {
+          GeneratorResponse::Yielding(piped) => {
+            resp = gen.query(query);
+            Some(piped)
+          }
+          done @ GeneratorResponse::Done(_) => {
+            resp = done;
+            None
+          }
+        };
+        next_resp = next.query(piped);
+      }
+    })
+  }
+}
+
+impl<Y, R, Q> GeneratorExt<Y, R, Q> for Running<Y, R, Q>
+where
+  Y: 'static,
+  R: 'static,
+  Q: 'static,
+{
+  type Adapted<Y2: 'static, R2: 'static, Q2: 'static> = Running<Y2, R2, Q2>;
+
+  fn adapt<Y2, R2, Q2, F, Fut>(self, f: F) -> Self::Adapted<Y2, R2, Q2>
+  where
+    F: FnOnce(YieldWrapper<Q2, Y2>, Running<Y, R, Q>) -> Fut + 'static,
+    Fut: Future<Output = R2> + 'static,
+    Y2: 'static,
+    R2: 'static,
+    Q2: 'static,
+  {
+    StartedGenerator::run_with(self, f)
+  }
+}
+
+impl<F, Fut, S, Y, R, Q> GeneratorExt<Y, R, Q> for Generator<F, Fut, S, Y, R, Q>
+where
+  F: FnOnce(YieldWrapper<Q, Y>, S) -> Fut + 'static,
+  Fut: Future<Output = R> + 'static,
+  S: 'static,
+  Y: 'static,
+  R: 'static,
+  Q: 'static,
+{
+  type Adapted<Y2: 'static, R2: 'static, Q2: 'static> =
+    BoxedGenerator<S, Y2, R2, Q2>;
+
+  fn adapt<Y2, R2, Q2, F2, Fut2>(self, f: F2) -> Self::Adapted<Y2, R2, Q2>
+  where
+    F2: FnOnce(YieldWrapper<Q2, Y2>, Running<Y, R, Q>) -> Fut2 + 'static,
+    Fut2: Future<Output = R2> + 'static,
+    Y2: 'static,
+    R2: 'static,
+    Q2: 'static,
+  {
+    let future_maker = self.into_future_maker();
+    Generator::new(Box::new(move |y, start| {
+      Box::pin(async move {
+        let running = StartedGenerator::run_with(start, future_maker);
+        f(y, running).await
+      }) as _
+    }))
+  }
+}
+
+/// Run `a`, then start `b` with whatever `a` returns and run that.
+///
+/// `b` must not have been started yet.
+pub fn chain<Y, R1, R2, Q, F, Fut>(
+  a: Running<Y, R1, Q>,
+  b: Generator<F, Fut, R1, Y, R2, Q>,
+) -> Running<Y, R2, Q>
+where
+  F: FnOnce(YieldWrapper<Q, Y>, R1) -> Fut + 'static,
+  Fut: Future<Output = R2> + 'static,
+  Y: 'static,
+  R1: 'static,
+  R2: 'static,
+  Q: 'static,
+{
+  let b = b.into_future_maker();
+  a.adapt(move |y, a| async move {
+    let r1 = relay(&y, a).await;
+    relay(&y, StartedGenerator::run_with(r1, b)).await
+  })
+}
+
+/// What [`zip`] returns: the last responses of both generators.
+pub type ZipDone<Y1, Y2, R1, R2> =
+  (GeneratorResponse<Y1, R1>, GeneratorResponse<Y2, R2>);
+
+/// Query two generators in lockstep, yielding their yields in pairs.
+///
+/// Each query is cloned and sent to both.
+/// Once either one returns, the zipped generator returns both of their
+/// last responses (so one of them might still be `Yielding`).
+pub fn zip<Y1, Y2, R1, R2, Q>(
+  a: Running<Y1, R1, Q>,
+  b: Running<Y2, R2, Q>,
+) -> Running<(Y1, Y2), ZipDone<Y1, Y2, R1, R2>, Q>
+where
+  Y1: 'static,
+  Y2: 'static,
+  R1: 'static,
+  R2: 'static,
+  Q: Clone + 'static,
+{
+  a.adapt(move |y, (mut a, mut a_resp)| async move {
+    let (mut b, mut b_resp) = b;
+    loop {
+      match (a_resp, b_resp) {
+        (
+          GeneratorResponse::Yielding(a_yielded),
+          GeneratorResponse::Yielding(b_yielded),
+        ) => {
+          let query: Q = y.ield((a_yielded, b_yielded)).await;
+          a_resp = a.query(query.clone());
+          b_resp = b.query(query);
+        }
+        done => return done,
+      }
+    }
+  })
+}
+
+/// Alternate between two generators, yielding from `a`, then `b`,
+/// then `a`, and so on.
+///
+/// Each query goes to the generator whose yield it's answering.
+/// Once one of them returns, this keeps going with just the other one,
+/// and returns both of their results at the end.
+pub fn interleave<Y, R1, R2, Q>(
+  a: Running<Y, R1, Q>,
+  b: Running<Y, R2, Q>,
+) -> Running<Y, (R1, R2), Q>
+where
+  Y: 'static,
+  R1: 'static,
+  R2: 'static,
+  Q: 'static,
+{
+  a.adapt(move |y, (mut a, mut a_resp)| async move {
+    let (mut b, mut b_resp) = b;
+    loop {
+      match (a_resp, b_resp) {
+        (
+          GeneratorResponse::Yielding(a_yielded),
+          GeneratorResponse::Yielding(b_yielded),
+        ) => {
+          a_resp = a.query(y.ield(a_yielded).await);
+          b_resp = b.query(y.ield(b_yielded).await);
+        }
+        (GeneratorResponse::Done(r1), b_resp) => {
+          return (r1, relay(&y, (b, b_resp)).await)
+        }
+        (a_resp, GeneratorResponse::Done(r2)) => {
+          return (relay(&y, (a, a_resp)).await, r2)
+        }
+      }
+    }
+  })
+}
+
+/// One of two things, for [`select`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<A, B> {
+  First(A),
+  Second(B),
+}
+
+/// Route each query to one of two generators,
+/// and yield whatever that generator yields back.
+///
+/// `pick` returns `true` to send the query to `a`, and `false` for `b`.
+///
+/// Every query is passed on to the generator it's routed to, and what that
+/// yields back is the reply. This starts out yielding `a`'s first yield;
+/// there's only room for one, so `b`'s first yield is dropped.
+/// Once either generator returns, so does this.
+pub fn select<Y, R1, R2, Q, P>(
+  a: Running<Y, R1, Q>,
+  b: Running<Y, R2, Q>,
+  mut pick: P,
+) -> Running<Y, Either<R1, R2>, Q>
+where
+  P: FnMut(&Q) -> bool + 'static,
+  Y: 'static,
+  R1: 'static,
+  R2: 'static,
+  Q: 'static,
+{
+  a.adapt(move |y, (mut a, a_resp)| async move {
+    let (mut b, b_resp) = b;
+    let mut yielded = match (a_resp, b_resp) {
+      (
+        GeneratorResponse::Yielding(a_yielded),
+        GeneratorResponse::Yielding(_),
+      ) => a_yielded,
+      (GeneratorResponse::Done(r1), _) => return Either::First(r1),
+      (_, GeneratorResponse::Done(r2)) => return Either::Second(r2),
+    };
+    loop {
+      let query = y.ield(yielded).await;
+      let resp = if pick(&query) {
+        a.query(query).map_return(Either::First)
+      } else {
+        b.query(query).map_return(Either::Second)
+      };
+      yielded = match resp {
+        GeneratorResponse::Yielding(next) => next,
+        GeneratorResponse::Done(done) => return done,
+      };
+    }
+  })
+}
+
+/// Pipe `a`'s yields into `b`'s queries.
+///
+/// This is the same as `a.then(b)`; see [`GeneratorExt::then`].
+pub fn pipe<YA, RA, QA, YB, RB>(
+  a: Running<YA, RA, QA>,
+  b: Running<YB, RB, Option<YA>>,
+) -> Running<YB, (Option<RA>, RB), QA>
+where
+  YA: 'static,
+  RA: 'static,
+  QA: 'static,
+  YB: 'static,
+  RB: 'static,
+{
+  a.then(b)
+}
+
+/// Pass everything through between `y` and a generator until it returns.
+async fn relay<Y, R, Q>(
+  y: &YieldWrapper<Q, Y>,
+  (mut gen, mut resp): Running<Y, R, Q>,
+) -> R {
+  loop {
+    match resp {
+      GeneratorResponse::Yielding(yielded) => {
+        let query = y.ield(yielded).await;
+        resp = gen.query(query);
+      }
+      GeneratorResponse::Done(done) => return done,
+    }
+  }
+}
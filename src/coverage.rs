@@ -0,0 +1,141 @@
+//! Yield-site coverage.
+//!
+//! Start a generator with [`Coverage::run`] (or [`watch`](Coverage::watch)
+//! one that's already going), and every `y.ield` it hits gets counted.
+//! Then print the [`Coverage::report`] to see which yield points your
+//! tests actually exercised.
+//!
+//! The report can only list sites that were hit at least once. Nothing
+//! knows where a `y.ield` is until it runs, so a site that was never hit
+//! doesn't show up with a count of 0; it just isn't there. Finding those is
+//! up to you, by comparing the report against the script's source.
+//!
+//! A single `Coverage` can be shared between many tests (say, in a
+//! `static LazyLock<Coverage>`) to collect coverage over a whole test suite.
+//!
+//! This is only compiled in with the `coverage` feature (or in this crate's
+//! own unit tests), so normal builds don't pay for it on every yield.
+
+use std::{
+  collections::BTreeMap,
+  fmt,
+  future::Future,
+  panic::Location,
+  rc::Rc,
+  sync::{Arc, Mutex},
+};
+
+use crate::{
+  GeneratorResponse, SharedSlot, StartedGenerator, YieldPoint, YieldWrapper,
+};
+
+/// Collects hit counts for yield sites.
+///
+/// This is a cheap handle; clones share the same counts.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+  hits: Arc<Mutex<BTreeMap<SiteKey, u64>>>,
+}
+
+type SiteKey = (&'static Location<'static>, Option<&'static str>);
+
+impl Coverage {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Create and start a generator, like [`StartedGenerator::run_with`],
+  /// counting every yield it hits from the very first one.
+  pub fn run_with<S, Y, R, Q, F, Fut>(
+    &self,
+    start: S,
+    f: F,
+  ) -> (StartedGenerator<Y, R, Q>, GeneratorResponse<Y, R>)
+  where
+    F: FnOnce(YieldWrapper<Q, Y>, S) -> Fut,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+  {
+    let slot = SharedSlot::new();
+    slot.coverage.borrow_mut().push(self.clone());
+    StartedGenerator::run_in(Rc::new(slot), start, f)
+  }
+
+  /// `run_with` a unit start.
+  pub fn run<Y, R, Q, F, Fut>(
+    &self,
+    f: F,
+  ) -> (StartedGenerator<Y, R, Q>, GeneratorResponse<Y, R>)
+  where
+    F: FnOnce(YieldWrapper<Q, Y>) -> Fut,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+  {
+    self.run_with((), move |y, ()| f(y))
+  }
+
+  /// Count every yield `gen` hits from now on.
+  ///
+  /// Watching the same generator twice doesn't count its yields twice.
+  pub fn watch<Y, R, Q>(&self, gen: &StartedGenerator<Y, R, Q>) {
+    let mut watchers = gen.swap_slot.coverage.borrow_mut();
+    if !watchers.iter().any(|c| Arc::ptr_eq(&c.hits, &self.hits)) {
+      watchers.push(self.clone());
+    }
+  }
+
+  /// How many times the yield site at `point` was hit.
+  pub fn hits_at(&self, point: YieldPoint) -> u64 {
+    let hits = self.hits.lock().unwrap();
+    hits
+      .get(&(point.location, point.label))
+      .copied()
+      .unwrap_or(0)
+  }
+
+  /// Every yield site hit so far, sorted by location.
+  ///
+  /// Sites that haven't been hit aren't in here at all; see the
+  /// [module](self) docs.
+  pub fn report(&self) -> CoverageReport {
+    let hits = self.hits.lock().unwrap();
+    let sites = hits
+      .iter()
+      .map(|(&(location, label), &count)| {
+        (YieldPoint { label, location }, count)
+      })
+      .collect();
+    CoverageReport { sites }
+  }
+
+  fn hit(&self, point: YieldPoint) {
+    let mut hits = self.hits.lock().unwrap();
+    *hits.entry((point.location, point.label)).or_insert(0) += 1;
+  }
+}
+
+/// Called whenever a generator body yields.
+pub(crate) fn record_hit<Q, Y>(slot: &SharedSlot<Q, Y>, point: YieldPoint) {
+  for coverage in slot.coverage.borrow().iter() {
+    coverage.hit(point);
+  }
+}
+
+/// A snapshot of hit counts per yield site.
+///
+/// The `Display` impl prints one site per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+  pub sites: Vec<(YieldPoint, u64)>,
+}
+
+impl fmt::Display for CoverageReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (point, count) in self.sites.iter() {
+      writeln!(f, "{:>8}  {}", count, point)?;
+    }
+    Ok(())
+  }
+}
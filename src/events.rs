@@ -0,0 +1,261 @@
+//! Waiting for particular queries.
+//!
+//! When `Q` is an event, a script usually only cares about a few kinds of
+//! them at a time. The `wait_for` family keeps yielding `Y::default()` and
+//! throwing queries away until one turns up that it's looking for.
+//!
+//! ```rust
+//! # use dedenne::*;
+//! #[derive(Debug, Clone, Copy, PartialEq)]
+//! enum Input {
+//!   Up,
+//!   Down,
+//!   Confirm,
+//!   Cancel,
+//! }
+//!
+//! let (mut gen, _) = StartedGenerator::run(|y| async move {
+//!   y.ield(Some("Press Confirm to start")).await;
+//!   y.wait_for(|input| *input == Input::Confirm).await;
+//!   y.ield(Some("Sure?")).await;
+//!   match y.wait_for_any(&[Input::Confirm, Input::Cancel]).await {
+//!     Input::Confirm => "started",
+//!     _ => "cancelled",
+//!   }
+//! });
+//! assert_eq!(gen.query(Input::Up), GeneratorResponse::Yielding(None));
+//! assert_eq!(gen.query(Input::Down), GeneratorResponse::Yielding(None));
+//! assert_eq!(
+//!   gen.query(Input::Confirm),
+//!   GeneratorResponse::Yielding(Some("Sure?"))
+//! );
+//! assert_eq!(gen.query(Input::Down), GeneratorResponse::Yielding(None));
+//! assert_eq!(gen.query(Input::Cancel), GeneratorResponse::Done("cancelled"));
+//! ```
+//!
+//! To see what's being thrown away (to log it, or pass it on to someone
+//! else), wait through [`y.on_discard(hook)`](YieldWrapper::on_discard)
+//! instead, which has all the same waits.
+
+use std::{cell::RefCell, future::Future};
+
+use crate::{YieldPoint, YieldWrapper};
+
+impl<Q, Y: Default> YieldWrapper<Q, Y> {
+  /// Wait for a query that `pred` likes, and return it.
+  #[track_caller]
+  pub fn wait_for<'a, P>(&'a self, pred: P) -> impl Future<Output = Q> + 'a
+  where
+    P: FnMut(&Q) -> bool + 'a,
+  {
+    let point = YieldPoint::here(Some("wait_for"));
+    forever(filter_within(self, point, None, matching(pred), drop))
+  }
+
+  /// Wait for a query that `f` turns into `Some`, and return that.
+  #[track_caller]
+  pub fn wait_for_map<'a, T, F>(&'a self, f: F) -> impl Future<Output = T> + 'a
+  where
+    F: FnMut(&Q) -> Option<T> + 'a,
+    T: 'a,
+  {
+    let point = YieldPoint::here(Some("wait_for_map"));
+    forever(filter_within(self, point, None, mapping(f), drop))
+  }
+
+  /// Wait for a query equal to any of `wanted`, and return it.
+  #[track_caller]
+  pub fn wait_for_any<'a>(
+    &'a self,
+    wanted: &'a [Q],
+  ) -> impl Future<Output = Q> + 'a
+  where
+    Q: PartialEq,
+  {
+    let point = YieldPoint::here(Some("wait_for_any"));
+    forever(filter_within(self, point, None, any_of(wanted), drop))
+  }
+
+  /// Like `wait_for`, but gives up and returns `None` if nothing
+  /// it likes turns up in the next `limit` queries.
+  #[track_caller]
+  pub fn wait_for_within<'a, P>(
+    &'a self,
+    limit: u32,
+    pred: P,
+  ) -> impl Future<Output = Option<Q>> + 'a
+  where
+    P: FnMut(&Q) -> bool + 'a,
+  {
+    let point = YieldPoint::here(Some("wait_for_within"));
+    filter_within(self, point, Some(limit), matching(pred), drop)
+  }
+
+  /// Like `wait_for_map`, but gives up and returns `None` if nothing
+  /// it likes turns up in the next `limit` queries.
+  #[track_caller]
+  pub fn wait_for_map_within<'a, T, F>(
+    &'a self,
+    limit: u32,
+    f: F,
+  ) -> impl Future<Output = Option<T>> + 'a
+  where
+    F: FnMut(&Q) -> Option<T> + 'a,
+    T: 'a,
+  {
+    let point = YieldPoint::here(Some("wait_for_map_within"));
+    filter_within(self, point, Some(limit), mapping(f), drop)
+  }
+}
+
+impl<Q, Y> YieldWrapper<Q, Y> {
+  /// Get a version of the `wait_for` family that passes every query it
+  /// throws away to `hook`, instead of dropping it.
+  pub fn on_discard<H>(&self, hook: H) -> OnDiscard<'_, Q, Y, H>
+  where
+    H: FnMut(Q),
+  {
+    OnDiscard {
+      y: self,
+      hook: RefCell::new(hook),
+    }
+  }
+}
+
+/// The `wait_for` family, but with somewhere for thrown-away queries to go.
+///
+/// Make one with [`y.on_discard(hook)`](YieldWrapper::on_discard).
+pub struct OnDiscard<'a, Q, Y, H> {
+  y: &'a YieldWrapper<Q, Y>,
+  hook: RefCell<H>,
+}
+
+impl<Q, Y: Default, H: FnMut(Q)> OnDiscard<'_, Q, Y, H> {
+  /// See [`YieldWrapper::wait_for`].
+  #[track_caller]
+  pub fn wait_for<'b, P>(&'b self, pred: P) -> impl Future<Output = Q> + 'b
+  where
+    P: FnMut(&Q) -> bool + 'b,
+  {
+    let point = YieldPoint::here(Some("wait_for"));
+    forever(filter_within(
+      self.y,
+      point,
+      None,
+      matching(pred),
+      self.hook(),
+    ))
+  }
+
+  /// See [`YieldWrapper::wait_for_map`].
+  #[track_caller]
+  pub fn wait_for_map<'b, T, F>(&'b self, f: F) -> impl Future<Output = T> + 'b
+  where
+    F: FnMut(&Q) -> Option<T> + 'b,
+    T: 'b,
+  {
+    let point = YieldPoint::here(Some("wait_for_map"));
+    forever(filter_within(self.y, point, None, mapping(f), self.hook()))
+  }
+
+  /// See [`YieldWrapper::wait_for_any`].
+  #[track_caller]
+  pub fn wait_for_any<'b>(
+    &'b self,
+    wanted: &'b [Q],
+  ) -> impl Future<Output = Q> + 'b
+  where
+    Q: PartialEq,
+  {
+    let point = YieldPoint::here(Some("wait_for_any"));
+    forever(filter_within(
+      self.y,
+      point,
+      None,
+      any_of(wanted),
+      self.hook(),
+    ))
+  }
+
+  /// See [`YieldWrapper::wait_for_within`].
+  #[track_caller]
+  pub fn wait_for_within<'b, P>(
+    &'b self,
+    limit: u32,
+    pred: P,
+  ) -> impl Future<Output = Option<Q>> + 'b
+  where
+    P: FnMut(&Q) -> bool + 'b,
+  {
+    let point = YieldPoint::here(Some("wait_for_within"));
+    filter_within(self.y, point, Some(limit), matching(pred), self.hook())
+  }
+
+  /// See [`YieldWrapper::wait_for_map_within`].
+  #[track_caller]
+  pub fn wait_for_map_within<'b, T, F>(
+    &'b self,
+    limit: u32,
+    f: F,
+  ) -> impl Future<Output = Option<T>> + 'b
+  where
+    F: FnMut(&Q) -> Option<T> + 'b,
+    T: 'b,
+  {
+    let point = YieldPoint::here(Some("wait_for_map_within"));
+    filter_within(self.y, point, Some(limit), mapping(f), self.hook())
+  }
+
+  fn hook(&self) -> impl FnMut(Q) + '_ {
+    |query| (self.hook.borrow_mut())(query)
+  }
+}
+
+/// Yield until `f` gives back `Ok`, or `limit` queries have gone by,
+/// handing everything else to `discard`.
+async fn filter_within<Q, Y, T, F, D>(
+  y: &YieldWrapper<Q, Y>,
+  point: YieldPoint,
+  limit: Option<u32>,
+  mut f: F,
+  mut discard: D,
+) -> Option<T>
+where
+  Y: Default,
+  F: FnMut(Q) -> Result<T, Q>,
+  D: FnMut(Q),
+{
+  let mut left = limit;
+  while left != Some(0) {
+    let query = y.ield_at(point, Y::default()).await;
+    match f(query) {
+      Ok(found) => return Some(found),
+      Err(query) => discard(query),
+    }
+    if let Some(left) = &mut left {
+      *left -= 1;
+    }
+  }
+  None
+}
+
+/// For waits with no limit, which can only end by finding something.
+async fn forever<T>(wait: impl Future<Output = Option<T>>) -> T {
+  wait.await.expect("there's no limit")
+}
+
+fn matching<Q>(
+  mut pred: impl FnMut(&Q) -> bool,
+) -> impl FnMut(Q) -> Result<Q, Q> {
+  move |query| if pred(&query) { Ok(query) } else { Err(query) }
+}
+
+fn mapping<Q, T>(
+  mut f: impl FnMut(&Q) -> Option<T>,
+) -> impl FnMut(Q) -> Result<T, Q> {
+  move |query| f(&query).ok_or(query)
+}
+
+fn any_of<Q: PartialEq>(wanted: &[Q]) -> impl FnMut(Q) -> Result<Q, Q> + '_ {
+  matching(|query| wanted.contains(query))
+}
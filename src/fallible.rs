@@ -0,0 +1,172 @@
+//! Generators that can fail.
+//!
+//! A [`TryGenerator`] is just a generator that returns a `Result`,
+//! so its body can use `?` to bail out early.
+//! Naming the type up front (instead of writing `StartedGenerator::run`)
+//! lets rustc work out what the `?`s convert into:
+//!
+//! ```rust
+//! # use dedenne::{fallible::TryGenerator, GeneratorResponse};
+//! # use std::num::ParseIntError;
+//! let (mut gen, resp) =
+//!   TryGenerator::<u32, u32, ParseIntError, &str>::run(|y| async move {
+//!     let a = y.ield(1).await.parse::<u32>()?;
+//!     let b = y.ield(2).await.parse::<u32>()?;
+//!     Ok(a + b)
+//!   });
+//! assert_eq!(resp, GeneratorResponse::Yielding(1));
+//! assert_eq!(gen.query("10"), GeneratorResponse::Yielding(2));
+//! assert!(matches!(gen.query("oops"), GeneratorResponse::Done(Err(_))));
+//! ```
+//!
+//! The yields can be fallible too, by yielding `Result`s.
+//! [`TryGeneratorIterator`] iterates over a generator like that,
+//! stopping at the first error.
+
+use std::{
+  fmt,
+  iter::{FusedIterator, Repeat},
+};
+
+use crate::{iter::GeneratorIterator, StartedGenerator};
+
+/// A generator that returns a `Result`.
+pub type TryGenerator<Y, T, E, Q = ()> = StartedGenerator<Y, Result<T, E>, Q>;
+
+impl<Y, T, E, Q> StartedGenerator<Result<Y, E>, Result<T, E>, Q> {
+  /// Like `iter_over`, but stops at the first error.
+  ///
+  /// See [`TryGeneratorIterator`].
+  pub fn try_iter_over<I>(
+    self,
+    iter: I,
+  ) -> TryGeneratorIterator<Y, T, E, Q, I> {
+    TryGeneratorIterator::new(self.iter_over(iter))
+  }
+}
+
+impl<Y, T, E> StartedGenerator<Result<Y, E>, Result<T, E>, ()> {
+  /// Like `iter`, but stops at the first error.
+  ///
+  /// See [`TryGeneratorIterator`].
+  pub fn try_iter(self) -> TryGeneratorIterator<Y, T, E, (), Repeat<()>> {
+    TryGeneratorIterator::new(self.iter())
+  }
+}
+
+/// Iterate over a generator that yields and returns `Result`s.
+///
+/// This gives you each `Ok` the generator yields. As soon as it fails,
+/// by yielding an `Err` or returning one, you get that `Err` and then
+/// iteration stops, so `collect::<Result<Vec<_>, _>>()` works.
+/// Then `consume_response` gives you either the generator's `Ok`
+/// return value or the error (again), whichever it was.
+/// That's why iterating needs `E: Clone`.
+pub struct TryGeneratorIterator<Y, T, E, Q, I> {
+  inner: GeneratorIterator<Result<Y, E>, Result<T, E>, Q, I>,
+  /// The error the generator yielded, if it did.
+  error: Option<E>,
+  /// Whether we've handed out an error or run out
+  stopped: bool,
+}
+
+impl<Y, T, E, Q, I> TryGeneratorIterator<Y, T, E, Q, I> {
+  /// Wrap an existing iterator (say, one made with `jumpstart_iter_over`).
+  pub fn new(
+    inner: GeneratorIterator<Result<Y, E>, Result<T, E>, Q, I>,
+  ) -> Self {
+    Self {
+      inner,
+      error: None,
+      stopped: false,
+    }
+  }
+
+  /// If the generator failed or returned, get what it failed with or
+  /// returned. Otherwise return `None`.
+  pub fn consume_response(self) -> Option<Result<T, E>> {
+    match self.error {
+      Some(error) => Some(Err(error)),
+      None => self.inner.consume_response(),
+    }
+  }
+
+  /// Peek at the error, if the generator failed.
+  pub fn error(&self) -> Option<&E> {
+    match (&self.error, self.inner.response()) {
+      (Some(error), _) | (None, Some(Err(error))) => Some(error),
+      _ => None,
+    }
+  }
+
+  /// Get back the plain iterator this is wrapping.
+  ///
+  /// If the generator failed by yielding an error, it's still waiting for
+  /// an answer to that yield, and you can `resume_with` it if you like.
+  pub fn into_inner(
+    self,
+  ) -> GeneratorIterator<Result<Y, E>, Result<T, E>, Q, I> {
+    self.inner
+  }
+}
+
+impl<Y, T, E, Q, I> Iterator for TryGeneratorIterator<Y, T, E, Q, I>
+where
+  I: Iterator<Item = Q>,
+  Y: 'static,
+  T: 'static,
+  E: Clone + 'static,
+  Q: 'static,
+{
+  type Item = Result<Y, E>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.stopped {
+      return None;
+    }
+    match self.inner.next() {
+      Some(Ok(yielded)) => Some(Ok(yielded)),
+      Some(Err(error)) => {
+        self.stopped = true;
+        self.error = Some(error.clone());
+        Some(Err(error))
+      }
+      None => {
+        self.stopped = true;
+        match self.inner.response() {
+          Some(Err(error)) => Some(Err(error.clone())),
+          _ => None,
+        }
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    if self.stopped {
+      (0, Some(0))
+    } else {
+      // It might return an error at the end
+      let upper = self.inner.size_hint().1;
+      (0, upper.and_then(|upper| upper.checked_add(1)))
+    }
+  }
+}
+
+impl<Y, T, E, Q, I> FusedIterator for TryGeneratorIterator<Y, T, E, Q, I>
+where
+  I: Iterator<Item = Q>,
+  Y: 'static,
+  T: 'static,
+  E: Clone + 'static,
+  Q: 'static,
+{
+}
+
+impl<Y, T, E, Q, I> fmt::Debug for TryGeneratorIterator<Y, T, E, Q, I> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("TryGeneratorIterator")
+      .field("inner", &self.inner)
+      .field("failed", &self.error().is_some())
+      .finish()
+  }
+}
@@ -1,10 +1,18 @@
 use std::{
+  cell::Cell,
   future::Future,
   pin::Pin,
+  rc::Rc,
   task::{Poll, RawWaker, RawWakerVTable, Waker},
 };
 
-use crate::{SwapSpace, SwapSpaceSlot};
+use crate::{SwapSpace, SwapSpaceSlot, YieldPoint};
+
+thread_local! {
+  /// The swap slot of the generator being stepped on this thread right now,
+  /// so we can tell when a `YieldWrapper` is used outside its own generator.
+  static RUNNING: Cell<*const ()> = const { Cell::new(std::ptr::null()) };
+}
 
 /// This is what `y.ield` returns.
 ///
@@ -16,33 +24,51 @@ use crate::{SwapSpace, SwapSpaceSlot};
 /// hopefully.
 pub(crate) struct YieldedFuture<Q, Y> {
   swap_slot: SwapSpaceSlot<Q, Y>,
+  /// Whether this has already returned `Ready`
+  done: bool,
 }
 
 impl<Q, Y> YieldedFuture<Q, Y> {
   // This function (closed over swap_slot) is the `yielder` function.
-  pub fn new(swap_slot: SwapSpaceSlot<Q, Y>, yielded: Y) -> Self {
-    // Immediately smuggle out the yielded value
-    let lock = swap_slot.borrow();
-    let is_yielding = matches!(&*lock, SwapSpace::Yielding(..));
-    drop(lock);
-
-    if is_yielding {
-      panic!("Found yielding state when making a new YieldedFuture. Be sure to remember the `.await` after!")
+  pub fn new(
+    swap_slot: SwapSpaceSlot<Q, Y>,
+    yielded: Y,
+    point: YieldPoint,
+  ) -> Self {
+    if !is_running(&swap_slot) {
+      not_running(&swap_slot, "yield", Some(point));
     }
 
-    let mut lock = swap_slot.borrow_mut();
-    match std::mem::replace(&mut *lock, SwapSpace::Yielding(yielded)) {
+    // Immediately smuggle out the yielded value
+    match swap_slot.space.replace(SwapSpace::Yielding(yielded)) {
       SwapSpace::ProcessingQuery | SwapSpace::JustStarted => {}
+      SwapSpace::Yielding(_) | SwapSpace::GotQuery(_) => {
+        let previous = swap_slot
+          .last_yield
+          .get()
+          .expect("a yielding generator always has a yield point");
+        panic!(
+          "Found yielding state when making a new YieldedFuture at {}; \
+          the yield at {} was never awaited. \
+          Be sure to remember the `.await` after!",
+          point, previous
+        )
+      }
       ono => unreachable!(
         "while making a new YieldedFuture, was in the illegal state {:?}",
         &ono
       ),
     }
-    drop(lock);
+    swap_slot.last_yield.set(Some(point));
+    #[cfg(any(test, feature = "coverage"))]
+    crate::coverage::record_hit(&swap_slot, point);
 
     // Wait until Self::poll is called to smuggle in the Q
     // Therefore it's on me to only ever do so once the user has filled it.
-    Self { swap_slot }
+    Self {
+      swap_slot,
+      done: false,
+    }
   }
 }
 
@@ -50,22 +76,29 @@ impl<Q, Y> Future for YieldedFuture<Q, Y> {
   type Output = Q;
 
   fn poll(
-    self: Pin<&mut Self>,
+    mut self: Pin<&mut Self>,
     _cx: &mut std::task::Context<'_>,
   ) -> std::task::Poll<Self::Output> {
-    let lock = self.swap_slot.borrow();
-    match &*lock {
+    if self.done {
+      panic!(
+        "A future returned by `y.ield` was polled again \
+        after it had already completed"
+      )
+    }
+    if !is_running(&self.swap_slot) {
+      let point = self.swap_slot.last_yield.get();
+      not_running(&self.swap_slot, "await a yield", point);
+    }
+
+    match self.swap_slot.space.replace(SwapSpace::ProcessingQuery) {
       // Just called y.ield
-      SwapSpace::Yielding(_) => std::task::Poll::Pending,
+      yielding @ SwapSpace::Yielding(_) => {
+        self.swap_slot.space.set(yielding);
+        std::task::Poll::Pending
+      }
       // Called from step_generator
-      SwapSpace::GotQuery(_) => {
-        drop(lock);
-        let mut lock = self.swap_slot.borrow_mut();
-        let query =
-          match std::mem::replace(&mut *lock, SwapSpace::ProcessingQuery) {
-            SwapSpace::GotQuery(q) => q,
-            _ => unreachable!(),
-          };
+      SwapSpace::GotQuery(query) => {
+        self.done = true;
         std::task::Poll::Ready(query)
       }
       ono => unreachable!(
@@ -76,8 +109,48 @@ impl<Q, Y> Future for YieldedFuture<Q, Y> {
   }
 }
 
+fn is_running<Q, Y>(swap_slot: &SwapSpaceSlot<Q, Y>) -> bool {
+  RUNNING.get() == Rc::as_ptr(swap_slot) as *const ()
+}
+
+/// Someone used a `YieldWrapper` while its generator wasn't the one running.
+#[cold]
+#[inline(never)]
+fn not_running<Q, Y>(
+  swap_slot: &SwapSpaceSlot<Q, Y>,
+  what: &str,
+  point: Option<YieldPoint>,
+) -> ! {
+  let what = match point {
+    Some(point) => format!("{} at {}", what, point),
+    None => what.to_string(),
+  };
+  let finished =
+    swap_slot.inspect(|space| matches!(space, SwapSpace::Finished));
+  if finished {
+    panic!(
+      "Tried to {} after its generator had finished; \
+      the YieldWrapper must have outlived its generator",
+      what
+    )
+  } else if RUNNING.get().is_null() {
+    panic!(
+      "Tried to {} outside of any generator; \
+      a YieldWrapper can only be used while its generator is being queried",
+      what
+    )
+  } else {
+    panic!(
+      "Tried to {} from inside a different generator; \
+      a YieldWrapper can only be used in its own generator's body",
+      what
+    )
+  }
+}
+
 // https://github.com/not-fl3/macroquad/blob/master/src/exec.rs
-fn waker() -> Waker {
+// Built once, so stepping a generator doesn't have to make a new one.
+static WAKER: Waker = {
   unsafe fn clone(data: *const ()) -> RawWaker {
     RawWaker::new(data, &VTABLE)
   }
@@ -96,14 +169,26 @@ fn waker() -> Waker {
     RawWakerVTable::new(clone, wake, wake_by_ref, drop);
   let raw_waker = RawWaker::new(std::ptr::null(), &VTABLE);
   unsafe { Waker::from_raw(raw_waker) }
-}
+};
 
 /// returns Some(T) if future is done, None if it would block
-pub(crate) fn resume<T>(
+///
+/// `swap_slot` is marked as the running generator while the future is polled.
+#[inline]
+pub(crate) fn resume<T, Q, Y>(
   future: &mut Pin<Box<dyn Future<Output = T>>>,
+  swap_slot: &SwapSpaceSlot<Q, Y>,
 ) -> Option<T> {
-  let waker = waker();
-  let mut futures_context = std::task::Context::from_waker(&waker);
+  // Put back whatever was running before, even if the body panics,
+  // for when one generator's body is driving another
+  struct Restore(*const ());
+  impl Drop for Restore {
+    fn drop(&mut self) {
+      RUNNING.set(self.0);
+    }
+  }
+  let _restore = Restore(RUNNING.replace(Rc::as_ptr(swap_slot) as *const ()));
+  let mut futures_context = std::task::Context::from_waker(&WAKER);
   match future.as_mut().poll(&mut futures_context) {
     Poll::Ready(v) => Some(v),
     Poll::Pending => None,
@@ -1,4 +1,4 @@
-use std::{future::Future, pin::Pin};
+use std::{fmt, future::Future, iter::FusedIterator, pin::Pin};
 
 use crate::{GeneratorResponse, StartedGenerator, YieldWrapper};
 
@@ -14,6 +14,8 @@ use crate::{GeneratorResponse, StartedGenerator, YieldWrapper};
 /// inner iterator runs out of `Q`s, or the generator returns its `R`.
 ///
 /// If you want the `R` at the end, you can call `consume_response` or `try_consume_response`.
+/// If the inner iterator ran out first, you can keep going by hand with
+/// `resume_with`, or take the generator back out with `into_parts`.
 pub struct GeneratorIterator<Y, R, Q, I> {
   inner: GeneratorIteratorState<Y, R, Q, I>,
 }
@@ -26,11 +28,104 @@ impl<Y, R, Q, I> GeneratorIterator<Y, R, Q, I> {
   /// If the inner generator ever responded, return the response.
   /// Otherwise return `None`.
   pub fn consume_response(self) -> Option<R> {
+    self.try_consume_response().ok()
+  }
+
+  /// If the inner generator ever responded, return the response.
+  /// Otherwise give this back.
+  pub fn try_consume_response(self) -> Result<R, Self> {
     match self.inner {
+      GeneratorIteratorState::GeneratorDone(response, _) => Ok(response),
+      inner => Err(Self { inner }),
+    }
+  }
+
+  /// Peek at the response, if the inner generator ever responded.
+  pub fn response(&self) -> Option<&R> {
+    match &self.inner {
       GeneratorIteratorState::GeneratorDone(response, _) => Some(response),
       _ => None,
     }
   }
+
+  /// Take apart this iterator into whatever it's holding on to.
+  pub fn into_parts(self) -> GeneratorIteratorParts<Y, R, Q, I> {
+    match self.inner {
+      GeneratorIteratorState::NoInitStart(maker, iter) => {
+        GeneratorIteratorParts::Unstarted(maker, iter)
+      }
+      GeneratorIteratorState::Running(generator, iter) => {
+        GeneratorIteratorParts::Running(generator, iter)
+      }
+      GeneratorIteratorState::ExhaustedIterator(generator, iter) => {
+        GeneratorIteratorParts::ExhaustedIterator(generator, iter)
+      }
+      GeneratorIteratorState::GeneratorDone(response, iter) => {
+        GeneratorIteratorParts::GeneratorDone(response, iter)
+      }
+      GeneratorIteratorState::Poisoned => panic_poisoned(),
+    }
+  }
+
+  /// Query the inner generator by hand, skipping the inner iterator.
+  ///
+  /// This is mostly useful for getting going again after the inner
+  /// iterator runs out. Returns what the generator yielded, or `None` if it
+  /// returned (which you can get with `consume_response`, same as usual).
+  ///
+  /// Panics if the generator hasn't been started yet.
+  /// Once the generator has returned, this always returns `None`.
+  pub fn resume_with(&mut self, query: Q) -> Option<Y> {
+    let (mut generator, iter, exhausted) = match std::mem::replace(
+      &mut self.inner,
+      GeneratorIteratorState::Poisoned,
+    ) {
+      GeneratorIteratorState::Running(generator, iter) => {
+        (generator, iter, false)
+      }
+      GeneratorIteratorState::ExhaustedIterator(generator, iter) => {
+        (generator, iter, true)
+      }
+      GeneratorIteratorState::NoInitStart(..) => {
+        panic!("Tried to resume a GeneratorIterator before it started; call `next` first")
+      }
+      done @ GeneratorIteratorState::GeneratorDone(..) => {
+        self.inner = done;
+        return None;
+      }
+      GeneratorIteratorState::Poisoned => panic_poisoned(),
+    };
+    match generator.query(query) {
+      GeneratorResponse::Yielding(yielded) => {
+        self.inner = if exhausted {
+          GeneratorIteratorState::ExhaustedIterator(generator, iter)
+        } else {
+          GeneratorIteratorState::Running(generator, iter)
+        };
+        Some(yielded)
+      }
+      GeneratorResponse::Done(result) => {
+        self.inner = GeneratorIteratorState::GeneratorDone(result, iter);
+        None
+      }
+    }
+  }
+
+  /// Move the generator and iterator out of `Running` into another state.
+  fn leave_running(
+    &mut self,
+    to: impl FnOnce(
+      StartedGenerator<Y, R, Q>,
+      I,
+    ) -> GeneratorIteratorState<Y, R, Q, I>,
+  ) {
+    match std::mem::replace(&mut self.inner, GeneratorIteratorState::Poisoned) {
+      GeneratorIteratorState::Running(generator, iter) => {
+        self.inner = to(generator, iter)
+      }
+      _ => unreachable!("leave_running is only called while Running"),
+    }
+  }
 }
 
 impl<Y, R, Q, I> Iterator for GeneratorIterator<Y, R, Q, I>
@@ -43,19 +138,27 @@ where
   type Item = Y;
 
   fn next(&mut self) -> Option<Self::Item> {
-    match &self.inner {
-      GeneratorIteratorState::NoInitStart(..) => {
-        // that's ergonomic
-        let (maker, iter) = match std::mem::replace(
-          &mut self.inner,
-          GeneratorIteratorState::TmpDodgeBorrowck,
-        ) {
-          GeneratorIteratorState::NoInitStart(a, b) => (a, b),
-          _ => unreachable!(),
-        };
+    // This is the hot path, so step in place
+    // instead of moving everything out and back
+    if let GeneratorIteratorState::Running(generator, iter) = &mut self.inner {
+      let Some(query) = iter.next() else {
+        self.leave_running(GeneratorIteratorState::ExhaustedIterator);
+        return None;
+      };
+      return match generator.query(query) {
+        GeneratorResponse::Yielding(yielded) => Some(yielded),
+        GeneratorResponse::Done(result) => {
+          self.leave_running(|_, iter| {
+            GeneratorIteratorState::GeneratorDone(result, iter)
+          });
+          None
+        }
+      };
+    }
 
+    match std::mem::replace(&mut self.inner, GeneratorIteratorState::Poisoned) {
+      GeneratorIteratorState::NoInitStart(maker, iter) => {
         let (started, resp) = StartedGenerator::run(maker);
-
         match resp {
           GeneratorResponse::Yielding(yielded) => {
             self.inner = GeneratorIteratorState::Running(started, iter);
@@ -67,53 +170,86 @@ where
           }
         }
       }
+      GeneratorIteratorState::Poisoned => panic_poisoned(),
+      other => {
+        self.inner = other;
+        None
+      }
+    }
+  }
 
-      GeneratorIteratorState::Running(..) => {
-        let (mut generator, mut iter) = match std::mem::replace(
-          &mut self.inner,
-          GeneratorIteratorState::TmpDodgeBorrowck,
-        ) {
-          GeneratorIteratorState::Running(a, b) => (a, b),
-          _ => unreachable!(),
-        };
-
-        let Some(next) = iter.next() else {
-          self.inner = GeneratorIteratorState::ExhaustedIterator(generator);
-          return None;
-        };
-        let gen_response = generator.query(next);
-        match gen_response {
-          GeneratorResponse::Yielding(yielded) => {
-            self.inner = GeneratorIteratorState::Running(generator, iter);
-            Some(yielded)
-          }
-          GeneratorResponse::Done(result) => {
-            self.inner = GeneratorIteratorState::GeneratorDone(result, iter);
-            None
-          }
-        }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    match &self.inner {
+      // Every yield after the first one needs a query
+      GeneratorIteratorState::NoInitStart(_, iter) => {
+        let (_, upper) = iter.size_hint();
+        (0, upper.and_then(|upper| upper.checked_add(1)))
+      }
+      GeneratorIteratorState::Running(_, iter) => {
+        let (_, upper) = iter.size_hint();
+        (0, upper)
       }
-      GeneratorIteratorState::ExhaustedIterator(_)
-      | GeneratorIteratorState::GeneratorDone(_, _)
-      | GeneratorIteratorState::TmpDodgeBorrowck => None,
+      GeneratorIteratorState::ExhaustedIterator(..)
+      | GeneratorIteratorState::GeneratorDone(..)
+      | GeneratorIteratorState::Poisoned => (0, Some(0)),
     }
   }
 }
 
+impl<Y, R, Q, I> FusedIterator for GeneratorIterator<Y, R, Q, I>
+where
+  I: Iterator<Item = Q>,
+  Y: 'static,
+  Q: 'static,
+  R: 'static,
+{
+}
+
+impl<Y, R, Q, I> fmt::Debug for GeneratorIterator<Y, R, Q, I> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let state = match &self.inner {
+      GeneratorIteratorState::NoInitStart(..) => "Unstarted",
+      GeneratorIteratorState::Running(..) => "Running",
+      GeneratorIteratorState::ExhaustedIterator(..) => "ExhaustedIterator",
+      GeneratorIteratorState::GeneratorDone(..) => "GeneratorDone",
+      GeneratorIteratorState::Poisoned => "Poisoned",
+    };
+    f.debug_struct("GeneratorIterator")
+      .field("state", &state)
+      .finish_non_exhaustive()
+  }
+}
+
+/// The closure for a generator that hasn't been started yet.
+pub type BoxedFutureMaker<Q, Y, R> =
+  Box<dyn FnOnce(YieldWrapper<Q, Y>) -> Pin<Box<dyn Future<Output = R>>>>;
+
+/// Everything a [`GeneratorIterator`] was holding on to,
+/// from [`GeneratorIterator::into_parts`].
+pub enum GeneratorIteratorParts<Y, R, Q, I> {
+  /// It was made with a `jumpstart` method and never got started.
+  Unstarted(BoxedFutureMaker<Q, Y, R>, I),
+  /// Neither the generator nor the inner iterator have run out yet.
+  Running(StartedGenerator<Y, R, Q>, I),
+  /// The inner iterator ran out.
+  /// The generator is still waiting for the answer to its last yield.
+  ExhaustedIterator(StartedGenerator<Y, R, Q>, I),
+  /// The generator returned. Whatever's left of the inner iterator is here.
+  GeneratorDone(R, I),
+}
+
 /// Tracks the state of the `GeneratorIterator`
 pub(crate) enum GeneratorIteratorState<Y, R, Q, I> {
-  NoInitStart(
-    Box<dyn FnOnce(YieldWrapper<Q, Y>) -> Pin<Box<dyn Future<Output = R>>>>,
-    I,
-  ),
+  NoInitStart(BoxedFutureMaker<Q, Y, R>, I),
   /// We are still in normal operation
   Running(StartedGenerator<Y, R, Q>, I),
   /// The inner iterator ran out
-  ExhaustedIterator(StartedGenerator<Y, R, Q>),
+  ExhaustedIterator(StartedGenerator<Y, R, Q>, I),
   /// The outer generator ran out
   GeneratorDone(R, I),
-
-  TmpDodgeBorrowck,
+  /// Only around while moving between the other states.
+  /// If it's still here afterwards, the generator panicked partway through.
+  Poisoned,
 }
 
 impl<Y, R, Q, I> GeneratorIteratorState<Y, R, Q, I>
@@ -127,14 +263,197 @@ where
     Q: 'static,
     Y: 'static,
   {
-    let clo = |y: YieldWrapper<Q, Y>| {
-      let fut = f(y);
-      Box::pin(fut) as Pin<Box<dyn Future<Output = R>>>
+    Self::NoInitStart(box_future_maker(f), iter)
+  }
+}
+
+#[cold]
+#[inline(never)]
+fn panic_poisoned() -> ! {
+  panic!(
+    "This iterator's generator panicked partway through, \
+    and it can't be used anymore"
+  )
+}
+
+fn box_future_maker<Q, Y, R, F, Fut>(f: F) -> BoxedFutureMaker<Q, Y, R>
+where
+  F: FnOnce(YieldWrapper<Q, Y>) -> Fut + 'static,
+  Fut: Future<Output = R> + 'static,
+{
+  let clo = |y: YieldWrapper<Q, Y>| {
+    let fut = f(y);
+    Box::pin(fut) as Pin<Box<dyn Future<Output = R>>>
+  };
+  Box::new(clo) as _
+}
+
+/// Iterate over a generator, working out each query from the last yield.
+///
+/// Every time the generator yields, the closure gets a look at the yield
+/// and returns the next query. Iteration stops when the closure returns
+/// `None` or the generator returns its `R`.
+///
+/// As with [`GeneratorIterator`], you can get the `R` at the end with
+/// `consume_response` or `try_consume_response`.
+pub struct FeedbackIterator<Y, R, Q, F> {
+  inner: FeedbackIteratorState<Y, R, Q>,
+  feedback: F,
+}
+
+impl<Y, R, Q, F> FeedbackIterator<Y, R, Q, F>
+where
+  F: FnMut(&Y) -> Option<Q>,
+{
+  pub(crate) fn new(
+    generator: StartedGenerator<Y, R, Q>,
+    query: Q,
+    feedback: F,
+  ) -> Self {
+    Self {
+      inner: FeedbackIteratorState::Running(generator, query),
+      feedback,
+    }
+  }
+
+  pub(crate) fn self_start<G, Fut>(f: G, feedback: F) -> Self
+  where
+    G: FnOnce(YieldWrapper<Q, Y>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+  {
+    Self {
+      inner: FeedbackIteratorState::NoInitStart(box_future_maker(f)),
+      feedback,
+    }
+  }
+
+  /// If the inner generator ever responded, return the response.
+  /// Otherwise return `None`.
+  pub fn consume_response(self) -> Option<R> {
+    self.try_consume_response().ok()
+  }
+
+  /// If the inner generator ever responded, return the response.
+  /// Otherwise give this back.
+  pub fn try_consume_response(self) -> Result<R, Self> {
+    match self.inner {
+      FeedbackIteratorState::GeneratorDone(response) => Ok(response),
+      inner => Err(Self {
+        inner,
+        feedback: self.feedback,
+      }),
+    }
+  }
+
+  /// Peek at the response, if the inner generator ever responded.
+  pub fn response(&self) -> Option<&R> {
+    match &self.inner {
+      FeedbackIteratorState::GeneratorDone(response) => Some(response),
+      _ => None,
+    }
+  }
+
+  /// Take the generator back out, if it's started and hasn't returned.
+  ///
+  /// It's still waiting for an answer to the last thing it yielded.
+  pub fn into_generator(self) -> Option<StartedGenerator<Y, R, Q>> {
+    match self.inner {
+      FeedbackIteratorState::Running(generator, _)
+      | FeedbackIteratorState::Stopped(generator) => Some(generator),
+      _ => None,
+    }
+  }
+
+  /// Work out what to do after the generator responds.
+  fn handle(
+    &mut self,
+    generator: StartedGenerator<Y, R, Q>,
+    resp: GeneratorResponse<Y, R>,
+  ) -> Option<Y> {
+    match resp {
+      GeneratorResponse::Yielding(yielded) => {
+        self.inner = match (self.feedback)(&yielded) {
+          Some(query) => FeedbackIteratorState::Running(generator, query),
+          None => FeedbackIteratorState::Stopped(generator),
+        };
+        Some(yielded)
+      }
+      GeneratorResponse::Done(result) => {
+        self.inner = FeedbackIteratorState::GeneratorDone(result);
+        None
+      }
+    }
+  }
+}
+
+impl<Y, R, Q, F> Iterator for FeedbackIterator<Y, R, Q, F>
+where
+  F: FnMut(&Y) -> Option<Q>,
+  Y: 'static,
+  Q: 'static,
+  R: 'static,
+{
+  type Item = Y;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match std::mem::replace(&mut self.inner, FeedbackIteratorState::Poisoned) {
+      FeedbackIteratorState::NoInitStart(maker) => {
+        let (generator, resp) = StartedGenerator::run(maker);
+        self.handle(generator, resp)
+      }
+      FeedbackIteratorState::Running(mut generator, query) => {
+        let resp = generator.query(query);
+        self.handle(generator, resp)
+      }
+      FeedbackIteratorState::Poisoned => panic_poisoned(),
+      other => {
+        self.inner = other;
+        None
+      }
+    }
+  }
+}
+
+impl<Y, R, Q, F> FusedIterator for FeedbackIterator<Y, R, Q, F>
+where
+  F: FnMut(&Y) -> Option<Q>,
+  Y: 'static,
+  Q: 'static,
+  R: 'static,
+{
+}
+
+impl<Y, R, Q, F> fmt::Debug for FeedbackIterator<Y, R, Q, F> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let state = match &self.inner {
+      FeedbackIteratorState::NoInitStart(..) => "Unstarted",
+      FeedbackIteratorState::Running(..) => "Running",
+      FeedbackIteratorState::Stopped(..) => "Stopped",
+      FeedbackIteratorState::GeneratorDone(..) => "GeneratorDone",
+      FeedbackIteratorState::Poisoned => "Poisoned",
     };
-    Self::NoInitStart(Box::new(clo) as _, iter)
+    f.debug_struct("FeedbackIterator")
+      .field("state", &state)
+      .finish_non_exhaustive()
   }
 }
 
+/// Tracks the state of the `FeedbackIterator`
+enum FeedbackIteratorState<Y, R, Q> {
+  NoInitStart(BoxedFutureMaker<Q, Y, R>),
+  /// We know what to query the generator with next
+  Running(StartedGenerator<Y, R, Q>, Q),
+  /// The feedback closure returned `None`
+  Stopped(StartedGenerator<Y, R, Q>),
+  /// The generator ran out
+  GeneratorDone(R),
+  /// Only around while moving between the other states.
+  /// If it's still here afterwards, the generator panicked partway through.
+  Poisoned,
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -0,0 +1,153 @@
+//! Generators that lend out their state instead of giving it away.
+//!
+//! A normal generator gives up ownership of whatever it yields, so if it's
+//! building up a big buffer it has to clone it on every yield.
+//! A [`LendingGenerator`] instead yields its value *by reference*:
+//! the body hands the value over with [`Lender::lend`], you get to look at
+//! it until your next query, and then the body gets it back to reuse.
+//!
+//! ```rust
+//! # use dedenne::{lending::LendingGenerator, GeneratorResponse};
+//! let mut gen = LendingGenerator::run(|y| async move {
+//!   let mut line = String::new();
+//!   for word in ["the", "quick", "brown", "fox"] {
+//!     line.push_str(word);
+//!     // Lend `line` out, and get it back with the next query
+//!     let ((), back) = y.lend(line).await;
+//!     line = back;
+//!     line.push(' ');
+//!   }
+//!   line.len()
+//! });
+//! assert_eq!(gen.current().map(String::as_str), Some("the"));
+//! assert_eq!(
+//!   gen.resume().map_yield(String::as_str),
+//!   GeneratorResponse::Yielding("the quick")
+//! );
+//! gen.resume();
+//! gen.resume();
+//! assert_eq!(gen.resume(), GeneratorResponse::Done(20));
+//! ```
+
+use std::{fmt, future::Future};
+
+use crate::{GeneratorResponse, StartedGenerator, YieldPoint, YieldWrapper};
+
+/// The type of `y` in a [`LendingGenerator`]'s body.
+pub struct Lender<Q, Y> {
+  y: YieldWrapper<(Q, Y), Y>,
+}
+
+impl<Q, Y> Lender<Q, Y> {
+  /// Lend `value` out to whoever's driving the generator.
+  ///
+  /// This suspends until the next query, then gives you back the query
+  /// along with `value`.
+  #[track_caller]
+  pub fn lend(&self, value: Y) -> impl Future<Output = (Q, Y)> {
+    self.y.ield_at(YieldPoint::here(None), value)
+  }
+}
+
+/// A generator whose yields are borrowed from it until the next query.
+///
+/// The owned-API equivalent is a
+/// [`StartedGenerator<Y, R, (Q, Y)>`](StartedGenerator) that always gets
+/// its last yield back; this keeps hold of that yield for you.
+pub struct LendingGenerator<Y, R, Q = ()> {
+  inner: StartedGenerator<Y, R, (Q, Y)>,
+  /// What the generator is lending us, or `None` once it's returned
+  lent: Option<Y>,
+}
+
+impl<Y, R, Q> LendingGenerator<Y, R, Q> {
+  /// Create and start a lending generator.
+  ///
+  /// Its first yield is available from [`current`](Self::current).
+  ///
+  /// Panics if it returns without ever lending anything;
+  /// use [`try_run`](Self::try_run) if that might happen.
+  pub fn run<F, Fut>(f: F) -> Self
+  where
+    F: FnOnce(Lender<Q, Y>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    Y: 'static,
+    Q: 'static,
+  {
+    Self::try_run(f).unwrap_or_else(|_| {
+      panic!("The lending generator returned without lending anything")
+    })
+  }
+
+  /// Like `run`, but if the generator returns straight away
+  /// you get its `R` back as an `Err` instead of a panic.
+  pub fn try_run<F, Fut>(f: F) -> Result<Self, R>
+  where
+    F: FnOnce(Lender<Q, Y>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    Y: 'static,
+    Q: 'static,
+  {
+    let (inner, resp) = StartedGenerator::run(|y| f(Lender { y }));
+    match resp {
+      GeneratorResponse::Yielding(lent) => Ok(Self {
+        inner,
+        lent: Some(lent),
+      }),
+      GeneratorResponse::Done(done) => Err(done),
+    }
+  }
+
+  /// Give back what the generator lent us and get the next thing.
+  ///
+  /// Panics if the generator has already returned.
+  pub fn query(&mut self, query: Q) -> GeneratorResponse<&Y, R> {
+    let lent = self.lent.take().unwrap_or_else(|| {
+      panic!("Tried to query a lending generator after it had finished")
+    });
+    match self.inner.query((query, lent)) {
+      GeneratorResponse::Yielding(lent) => {
+        GeneratorResponse::Yielding(self.lent.insert(lent))
+      }
+      GeneratorResponse::Done(done) => GeneratorResponse::Done(done),
+    }
+  }
+
+  /// Look at the value currently lent out,
+  /// or `None` if the generator has returned.
+  pub fn current(&self) -> Option<&Y> {
+    self.lent.as_ref()
+  }
+
+  /// Mutably borrow the value currently lent out.
+  /// The generator sees your changes when it gets it back.
+  pub fn current_mut(&mut self) -> Option<&mut Y> {
+    self.lent.as_mut()
+  }
+
+  /// Whether the generator has returned.
+  pub fn is_finished(&self) -> bool {
+    self.lent.is_none()
+  }
+
+  /// See [`StartedGenerator::suspended_at`].
+  pub fn suspended_at(&self) -> Option<YieldPoint> {
+    self.inner.suspended_at()
+  }
+}
+
+impl<Y, R> LendingGenerator<Y, R, ()> {
+  /// Convenience function for generators that take `()` as their query.
+  pub fn resume(&mut self) -> GeneratorResponse<&Y, R> {
+    self.query(())
+  }
+}
+
+impl<Y, R, Q> fmt::Debug for LendingGenerator<Y, R, Q> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("LendingGenerator")
+      .field("finished", &self.is_finished())
+      .field("suspended_at", &self.suspended_at())
+      .finish_non_exhaustive()
+  }
+}
@@ -1,14 +1,36 @@
 #![doc = include_str!("../README.md")]
 
+pub mod batch;
+pub mod budget;
+pub mod combinators;
+#[cfg(any(test, feature = "coverage"))]
+pub mod coverage;
+pub mod events;
+pub mod fallible;
 mod futuring;
 pub mod iter;
+pub mod lending;
+mod macros;
+pub mod parallel;
+pub mod scheduler;
+pub mod sink;
+pub mod time;
+pub mod transduce;
+pub mod tween;
 pub mod wrapper;
+pub use budget::Budget;
+pub use combinators::GeneratorExt;
+pub use transduce::TransduceExt;
 pub use wrapper::Generator;
 
-use std::{cell::RefCell, future::Future, pin::Pin, sync::Arc};
+use std::{
+  cell::Cell, fmt, future::Future, ops::ControlFlow, panic::Location, pin::Pin,
+  rc::Rc,
+};
 
+use budget::BudgetTracker;
 use futuring::YieldedFuture;
-use iter::{GeneratorIterator, GeneratorIteratorState};
+use iter::{FeedbackIterator, GeneratorIterator, GeneratorIteratorState};
 
 /// Wraps an async function into something that can be used as a generator.
 ///
@@ -56,7 +78,21 @@ impl<Y, R, Q> StartedGenerator<Y, R, Q> {
     Q: 'static,
     Y: 'static,
   {
-    let state = Arc::new(RefCell::new(SwapSpace::JustStarted));
+    Self::run_in(Rc::new(SharedSlot::new()), start, f)
+  }
+
+  /// `run_with`, but with a swap slot that's already been set up.
+  fn run_in<S, F, Fut>(
+    state: SwapSpaceSlot<Q, Y>,
+    start: S,
+    f: F,
+  ) -> (Self, GeneratorResponse<Y, R>)
+  where
+    F: FnOnce(YieldWrapper<Q, Y>, S) -> Fut,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+  {
     let y = YieldWrapper::new(state.clone());
     let fut = f(y, start);
 
@@ -77,27 +113,59 @@ impl<Y, R, Q> StartedGenerator<Y, R, Q> {
     Q: 'static,
     Y: 'static,
   {
-    let inner_fut = |y, ()| async move { f(y).await };
-    StartedGenerator::run_with::<(), _, _>((), inner_fut)
+    StartedGenerator::run_with((), move |y, ()| f(y))
   }
 
   pub fn query(&mut self, query: Q) -> GeneratorResponse<Y, R> {
-    let mut lock = self.swap_slot.borrow_mut();
-    match std::mem::replace(&mut *lock, SwapSpace::GotQuery(query)) {
+    match self.swap_slot.space.replace(SwapSpace::GotQuery(query)) {
       SwapSpace::WaitingForQuery => {} // all good
-      SwapSpace::Finished => {
-        panic!("Tried to query a generator after it had finished")
-      }
-      ono => unreachable!(
-        "Tried to query a generator while in {:?}, an illegal state",
-        &ono
-      ),
-    };
-    drop(lock);
+      ono => self.swap_slot.bad_query(ono),
+    }
 
     self.step_generator()
   }
 
+  /// Whether the generator has returned its `R`.
+  pub fn is_finished(&self) -> bool {
+    self
+      .swap_slot
+      .inspect(|space| matches!(space, SwapSpace::Finished))
+  }
+
+  /// Whether the generator panicked while it was being queried.
+  /// Querying it again will panic.
+  pub fn is_poisoned(&self) -> bool {
+    self.swap_slot.inspect(|space| {
+      !matches!(space, SwapSpace::WaitingForQuery | SwapSpace::Finished)
+    })
+  }
+
+  /// Which `y.ield` the generator is currently suspended on,
+  /// or `None` if it's finished.
+  pub fn suspended_at(&self) -> Option<YieldPoint> {
+    self.swap_slot.last_yield.get()
+  }
+
+  /// Query the generator, letting it run through its
+  /// [`checkpoint`](YieldWrapper::checkpoint)s until it uses up the `budget`.
+  ///
+  /// This returns once the generator `y.ield`s, returns, or hits a checkpoint
+  /// after the budget is gone (in which case you get the checkpoint's
+  /// "still working" value).
+  ///
+  /// Outside of this method (including the initial step done by `run`),
+  /// there's no budget to spend, so every checkpoint suspends.
+  pub fn query_within(
+    &mut self,
+    budget: Budget,
+    query: Q,
+  ) -> GeneratorResponse<Y, R> {
+    self.swap_slot.budget.set(Some(budget.begin()));
+    let out = self.query(query);
+    self.swap_slot.budget.set(None);
+    out
+  }
+
   /// Create an iterator that repeatedly feeds another iterator into this.
   /// In order to call this method the iterator needs to have already been started.
   ///
@@ -120,14 +188,62 @@ impl<Y, R, Q> StartedGenerator<Y, R, Q> {
     GeneratorIterator::new(GeneratorIteratorState::self_start(f, iter))
   }
 
+  /// Create an iterator that works out each query from the previous yield.
+  ///
+  /// `query` answers the yield the generator is currently suspended on
+  /// (the one you already got from `run` or `query`);
+  /// after that, `feedback` looks at each yield and picks the next query,
+  /// or returns `None` to stop.
+  ///
+  /// The generator doesn't hang on to that first yield once it's handed it
+  /// to you, so `feedback` can't work out the first query itself; that's
+  /// why you pass it in. If you'd rather not,
+  /// [`jumpstart_iter_with`](Self::jumpstart_iter_with) starts the generator
+  /// for you and runs `feedback` on the very first yield too.
+  ///
+  /// See [`FeedbackIterator`].
+  pub fn iter_with<F>(
+    self,
+    query: Q,
+    feedback: F,
+  ) -> FeedbackIterator<Y, R, Q, F>
+  where
+    F: FnMut(&Y) -> Option<Q>,
+  {
+    FeedbackIterator::new(self, query, feedback)
+  }
+
+  pub fn jumpstart_iter_with<F, G, Fut>(
+    feedback: F,
+    f: G,
+  ) -> FeedbackIterator<Y, R, Q, F>
+  where
+    F: FnMut(&Y) -> Option<Q>,
+    G: FnOnce(YieldWrapper<Q, Y>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+  {
+    FeedbackIterator::self_start(f, feedback)
+  }
+
   fn step_generator(&mut self) -> GeneratorResponse<Y, R> {
-    let result = futuring::resume(&mut self.gen_func);
-    let mut lock = self.swap_slot.borrow_mut();
+    let result = futuring::resume(&mut self.gen_func, &self.swap_slot);
     if let Some(finished) = result {
-      match std::mem::replace(&mut *lock, SwapSpace::Finished) {
+      let last_yield = self.swap_slot.last_yield.take();
+      match self.swap_slot.space.replace(SwapSpace::Finished) {
         // we are "processing" it because we aren't able to call the code that says
         // we're finished (?)
         SwapSpace::ProcessingQuery => GeneratorResponse::Done(finished),
+        // The closure returned before its first yield, like a script that
+        // checks there's something to do first. That's a perfectly good
+        // (if short) generator, not an illegal state.
+        SwapSpace::JustStarted => GeneratorResponse::Done(finished),
+        SwapSpace::Yielding(_) | SwapSpace::GotQuery(_) => panic!(
+          "The generator returned without awaiting the yield at {}. \
+          Be sure to remember the `.await` after!",
+          last_yield.expect("a yielding generator always has a yield point")
+        ),
         ono => {
           unreachable!(
             "When the closure returned, was in illegal state {:?}",
@@ -136,8 +252,18 @@ impl<Y, R, Q> StartedGenerator<Y, R, Q> {
         }
       }
     } else {
-      match std::mem::replace(&mut *lock, SwapSpace::WaitingForQuery) {
+      match self.swap_slot.space.replace(SwapSpace::WaitingForQuery) {
         SwapSpace::Yielding(y) => GeneratorResponse::Yielding(y),
+        ono @ (SwapSpace::ProcessingQuery
+        | SwapSpace::JustStarted
+        | SwapSpace::GotQuery(_)) => {
+          self.swap_slot.space.set(ono);
+          panic!(
+            "The generator suspended without yielding. \
+            Only futures from `y.ield` and friends can be awaited \
+            inside a generator"
+          )
+        }
         ono => {
           unreachable!(
             "When the closure yielded, was in illegal state {:?}",
@@ -149,12 +275,27 @@ impl<Y, R, Q> StartedGenerator<Y, R, Q> {
   }
 }
 
+impl<Y, R, Q> fmt::Debug for StartedGenerator<Y, R, Q> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut out = f.debug_struct("StartedGenerator");
+    self.swap_slot.inspect(|state| out.field("state", state));
+    out
+      .field("suspended_at", &self.suspended_at())
+      .finish_non_exhaustive()
+  }
+}
+
 impl<Y, R> StartedGenerator<Y, R, ()> {
   /// Convenience wrapper for `query(())`, or querying with a unit.
   pub fn resume(&mut self) -> GeneratorResponse<Y, R> {
     self.query(())
   }
 
+  /// Convenience wrapper for `query_within(budget, ())`.
+  pub fn resume_within(&mut self, budget: Budget) -> GeneratorResponse<Y, R> {
+    self.query_within(budget, ())
+  }
+
   /// Create an iterator that repeatedly feeds () into this.
   pub fn iter(self) -> GeneratorIterator<Y, R, (), std::iter::Repeat<()>> {
     self.iter_over(std::iter::repeat(()))
@@ -172,6 +313,15 @@ impl<Y, R> StartedGenerator<Y, R, ()> {
   }
 }
 
+impl<Y: 'static, R: 'static> IntoIterator for StartedGenerator<Y, R, ()> {
+  type Item = Y;
+  type IntoIter = GeneratorIterator<Y, R, (), std::iter::Repeat<()>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
 /// The result of querying a generator.
 /// Either it will `Y`ield a value, or be done and return a `R`esponse.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -180,20 +330,191 @@ pub enum GeneratorResponse<Y, R> {
   Done(R),
 }
 
+impl<Y, R> GeneratorResponse<Y, R> {
+  pub fn map_yield<Y2>(
+    self,
+    f: impl FnOnce(Y) -> Y2,
+  ) -> GeneratorResponse<Y2, R> {
+    match self {
+      GeneratorResponse::Yielding(y) => GeneratorResponse::Yielding(f(y)),
+      GeneratorResponse::Done(r) => GeneratorResponse::Done(r),
+    }
+  }
+
+  pub fn map_return<R2>(
+    self,
+    f: impl FnOnce(R) -> R2,
+  ) -> GeneratorResponse<Y, R2> {
+    match self {
+      GeneratorResponse::Yielding(y) => GeneratorResponse::Yielding(y),
+      GeneratorResponse::Done(r) => GeneratorResponse::Done(f(r)),
+    }
+  }
+}
+
+impl<Y, T, E> GeneratorResponse<Y, Result<T, E>> {
+  /// Pull the error out of a failed generator's response,
+  /// so you can `?` it.
+  pub fn transpose(self) -> Result<GeneratorResponse<Y, T>, E> {
+    match self {
+      GeneratorResponse::Yielding(y) => Ok(GeneratorResponse::Yielding(y)),
+      GeneratorResponse::Done(r) => r.map(GeneratorResponse::Done),
+    }
+  }
+}
+
+/// `Yielding` is `Continue`, `Done` is `Break`.
+impl<Y, R> From<GeneratorResponse<Y, R>> for ControlFlow<R, Y> {
+  fn from(resp: GeneratorResponse<Y, R>) -> Self {
+    match resp {
+      GeneratorResponse::Yielding(y) => ControlFlow::Continue(y),
+      GeneratorResponse::Done(r) => ControlFlow::Break(r),
+    }
+  }
+}
+
+impl<Y, R> From<ControlFlow<R, Y>> for GeneratorResponse<Y, R> {
+  fn from(flow: ControlFlow<R, Y>) -> Self {
+    match flow {
+      ControlFlow::Continue(y) => GeneratorResponse::Yielding(y),
+      ControlFlow::Break(r) => GeneratorResponse::Done(r),
+    }
+  }
+}
+
+/// `Yielding` is `Ok`, `Done` is `Err`, same as with `ControlFlow`.
+/// That way you can `?` your way out of a loop once the generator's done.
+impl<Y, R> From<GeneratorResponse<Y, R>> for Result<Y, R> {
+  fn from(resp: GeneratorResponse<Y, R>) -> Self {
+    match resp {
+      GeneratorResponse::Yielding(y) => Ok(y),
+      GeneratorResponse::Done(r) => Err(r),
+    }
+  }
+}
+
+impl<Y, R> From<Result<Y, R>> for GeneratorResponse<Y, R> {
+  fn from(result: Result<Y, R>) -> Self {
+    match result {
+      Ok(y) => GeneratorResponse::Yielding(y),
+      Err(r) => GeneratorResponse::Done(r),
+    }
+  }
+}
+
 /// The type of `y` in `y.ield(foo)`.
+///
+/// # Panics
+///
+/// A `YieldWrapper` only works inside its own generator's body,
+/// while that generator is being queried.
+/// Misusing it panics with a message saying which of these you did:
+///
+/// - Yielding without `.await`ing the previous yield
+///   ("... was never awaited"), or returning without awaiting the last one
+///   ("returned without awaiting the yield").
+/// - Yielding after its generator has finished, say because you stashed the
+///   `YieldWrapper` somewhere that outlived it
+///   ("after its generator had finished").
+/// - Yielding while the generator isn't being queried
+///   ("outside of any generator").
+/// - Yielding from the body of some other generator
+///   ("from inside a different generator").
+/// - Polling the future `y.ield` returned again after it completed
+///   ("polled again after it had already completed").
+/// - Awaiting some other future that returns `Pending`
+///   ("suspended without yielding").
 pub struct YieldWrapper<Q, Y> {
   swap_slot: SwapSpaceSlot<Q, Y>,
+  /// Time left over from the last timed wait; see the [`time`] module.
+  carry: Cell<f32>,
 }
 
 impl<Q, Y> YieldWrapper<Q, Y> {
   pub(crate) fn new(swap_slot: SwapSpaceSlot<Q, Y>) -> Self {
-    Self { swap_slot }
+    Self {
+      swap_slot,
+      carry: Cell::new(0.0),
+    }
   }
 
   /// Call this as `y.ield`. It returns a future that returns your querying type.
   /// Control flow will return to the inner closure once the user calls `generator.query`
+  #[track_caller]
   pub fn ield(&self, yielded: Y) -> impl Future<Output = Q> {
-    YieldedFuture::new(self.swap_slot.clone(), yielded)
+    self.ield_at(YieldPoint::here(None), yielded)
+  }
+
+  /// Like `ield`, but attaches a label to this yield point,
+  /// which shows up in [`StartedGenerator::suspended_at`].
+  #[track_caller]
+  pub fn ield_labeled(
+    &self,
+    label: &'static str,
+    yielded: Y,
+  ) -> impl Future<Output = Q> {
+    self.ield_at(YieldPoint::here(Some(label)), yielded)
+  }
+
+  fn ield_at(&self, point: YieldPoint, yielded: Y) -> YieldedFuture<Q, Y> {
+    YieldedFuture::new(self.swap_slot.clone(), yielded, point)
+  }
+
+  /// Call this as `y.checkpoint(foo).await` in long-running loops.
+  ///
+  /// This suspends (yielding `still_working`) if the [`Budget`] from
+  /// [`query_within`](StartedGenerator::query_within) is used up, or if
+  /// the generator was queried without one, in which case it's just like
+  /// `ield`. Then it returns `Some` with the query it was resumed with.
+  /// While there's budget left, it returns `None` without suspending.
+  #[track_caller]
+  pub fn checkpoint(
+    &self,
+    still_working: Y,
+  ) -> impl Future<Output = Option<Q>> + '_ {
+    let point = YieldPoint::here(Some("checkpoint"));
+    async move {
+      let out_of_budget = match self.swap_slot.budget.get() {
+        Some(mut tracker) => {
+          let out = tracker.spend();
+          self.swap_slot.budget.set(Some(tracker));
+          out
+        }
+        None => true,
+      };
+      if out_of_budget {
+        Some(self.ield_at(point, still_working).await)
+      } else {
+        None
+      }
+    }
+  }
+}
+
+/// Where a generator's body called `y.ield` from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YieldPoint {
+  /// The label passed to `ield_labeled`, if any.
+  pub label: Option<&'static str>,
+  pub location: &'static Location<'static>,
+}
+
+impl YieldPoint {
+  #[track_caller]
+  fn here(label: Option<&'static str>) -> Self {
+    Self {
+      label,
+      location: Location::caller(),
+    }
+  }
+}
+
+impl fmt::Display for YieldPoint {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.label {
+      Some(label) => write!(f, "{:?} at {}", label, self.location),
+      None => write!(f, "{}", self.location),
+    }
   }
 }
 
@@ -216,4 +537,60 @@ enum SwapSpace<Q, Y> {
   Finished,
 }
 
-type SwapSpaceSlot<Q, Y> = Arc<RefCell<SwapSpace<Q, Y>>>;
+/// Everything shared between a generator and its `YieldWrapper`.
+///
+/// This is all `Cell`s rather than `RefCell`s, because it's on the hot path:
+/// each state transition is a single `replace`.
+struct SharedSlot<Q, Y> {
+  space: Cell<SwapSpace<Q, Y>>,
+  /// The budget being spent by `query_within`, if any.
+  budget: Cell<Option<BudgetTracker>>,
+  /// The `y.ield` the body most recently called.
+  last_yield: Cell<Option<YieldPoint>>,
+  /// The collectors counting this generator's yields.
+  #[cfg(any(test, feature = "coverage"))]
+  coverage: std::cell::RefCell<Vec<coverage::Coverage>>,
+}
+
+impl<Q, Y> SharedSlot<Q, Y> {
+  fn new() -> Self {
+    Self {
+      space: Cell::new(SwapSpace::JustStarted),
+      budget: Cell::new(None),
+      last_yield: Cell::new(None),
+      #[cfg(any(test, feature = "coverage"))]
+      coverage: std::cell::RefCell::new(Vec::new()),
+    }
+  }
+
+  /// Look at the state without changing it.
+  fn inspect<T>(&self, f: impl FnOnce(&SwapSpace<Q, Y>) -> T) -> T {
+    let space = self.space.replace(SwapSpace::Finished);
+    let out = f(&space);
+    self.space.set(space);
+    out
+  }
+
+  /// `query` found the generator in a state other than `WaitingForQuery`.
+  #[cold]
+  #[inline(never)]
+  fn bad_query(&self, ono: SwapSpace<Q, Y>) -> ! {
+    let message = match &ono {
+      SwapSpace::Finished => {
+        "Tried to query a generator after it had finished".to_string()
+      }
+      // Stepping always leaves us in one of the above,
+      // unless it panicked partway through
+      ono => format!(
+        "Tried to query a generator after it panicked (it was left in {:?})",
+        ono
+      ),
+    };
+    self.space.set(ono);
+    panic!("{}", message)
+  }
+}
+
+/// The generator and its `YieldWrapper` live on the same thread,
+/// so there's no need for atomic refcounts.
+type SwapSpaceSlot<Q, Y> = Rc<SharedSlot<Q, Y>>;
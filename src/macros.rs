@@ -0,0 +1,104 @@
+/**
+Make a [`Generator`](crate::wrapper::Generator) with less noise.
+
+Inside the body, use `yield_!(foo)` instead of `y.ield(foo).await`.
+It awaits for you, so you can't forget the `.await`.
+`yield_!("some label" => foo)` is the same as `y.ield_labeled`.
+
+```rust
+# use dedenne::*;
+let mut generator = gen!(start => {
+  for x in 0..start {
+    yield_!(x);
+  }
+  "All done!"
+});
+
+assert_eq!(generator.start(2), GeneratorResponse::Yielding(0));
+assert_eq!(generator.resume(), GeneratorResponse::Yielding(1));
+assert_eq!(generator.resume(), GeneratorResponse::Done("All done!"));
+```
+
+The start argument can be any pattern that's a single token tree
+(like `start`, `_`, or `(a, b)`).
+You can also declare the `S`, `Y`, `Q` and `R` types inline,
+in that order; all of them are optional.
+
+```rust
+# use dedenne::*;
+let mut generator = gen!(greeting: &str, yield String, query u32, return u32 => {
+  let a = yield_!("ask a" => format!("{} a", greeting));
+  let b = yield_!(format!("{} b", greeting));
+  a + b
+});
+
+assert!(matches!(generator.start("hi"), GeneratorResponse::Yielding(_)));
+assert!(matches!(generator.query(1), GeneratorResponse::Yielding(_)));
+assert_eq!(generator.query(2), GeneratorResponse::Done(3));
+```
+
+(`gen` is a reserved keyword in the 2024 edition,
+so there you'll have to call this as `r#gen!`.)
+*/
+#[macro_export]
+macro_rules! gen {
+  (
+    $start:tt $(: $s:ty)?
+    $(, yield $y:ty)?
+    $(, query $q:ty)?
+    $(, return $r:ty)?
+    => $body:block
+  ) => {
+    $crate::__gen_impl!(
+      ($) $start [$($s)?] [$($y)?] [$($q)?] [$($r)?] $body
+    )
+  };
+}
+
+/// Implementation detail of `gen!`.
+///
+/// The `$d` is a literal `$`, smuggled in so we can define `yield_!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gen_impl {
+  (
+    ($d:tt) $start:tt
+    [$($s:ty)?] [$($y:ty)?] [$($q:ty)?] [$($r:ty)?]
+    $body:block
+  ) => {
+    $crate::wrapper::Generator::new(
+      move |__y: $crate::YieldWrapper<
+        $crate::__gen_ty!($($q)?),
+        $crate::__gen_ty!($($y)?),
+      >,
+        $start: $crate::__gen_ty!($($s)?)| async move {
+        #[allow(unused_macros)]
+        macro_rules! yield_ {
+          () => {
+            __y.ield(()).await
+          };
+          ($d label:literal => $d e:expr) => {
+            __y.ield_labeled($d label, $d e).await
+          };
+          ($d e:expr) => {
+            __y.ield($d e).await
+          };
+        }
+        let __out: $crate::__gen_ty!($($r)?) = $body;
+        __out
+      },
+    )
+  };
+}
+
+/// Implementation detail of `gen!`. Infers a type if it wasn't given.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gen_ty {
+  () => {
+    _
+  };
+  ($t:ty) => {
+    $t
+  };
+}
@@ -0,0 +1,377 @@
+//! Stepping lots of generators at once, on several threads.
+//!
+//! Generators aren't `Send` (they can't be, what with the `YieldWrapper`
+//! being an `Rc` in disguise), so a [`ParallelSet`] never moves one between
+//! threads. Instead, each generator is *started* on one of its worker
+//! threads and lives there until it's done. Only the function that starts
+//! it, its queries, and what it yields and returns have to be `Send`.
+//!
+//! Each tick, every worker steps its own generators while the others
+//! step theirs, and then the results are put back in spawn order,
+//! so you get the same thing no matter how many threads there are or
+//! which one finished first.
+//!
+//! ```rust
+//! # use dedenne::parallel::ParallelSet;
+//! let mut set = ParallelSet::with_threads(4);
+//! let handles = (0..8u64)
+//!   .map(|id| {
+//!     let (handle, _) = set.spawn(move |y| async move {
+//!       let mut total = 0u64;
+//!       for step in 0..3 {
+//!         // Pretend this is something expensive
+//!         total += id * step;
+//!         let dt: u64 = y.ield(total).await;
+//!         total += dt;
+//!       }
+//!       total
+//!     });
+//!     handle
+//!   })
+//!   .collect::<Vec<_>>();
+//!
+//! let yields = set.tick_all(1);
+//! assert_eq!(
+//!   yields.iter().map(|(handle, _)| *handle).collect::<Vec<_>>(),
+//!   handles
+//! );
+//! set.tick_all(1);
+//! set.tick_all(1);
+//! assert!(set.is_empty());
+//! assert_eq!(set.take_finished().len(), 8);
+//! ```
+
+use std::{
+  any::Any,
+  collections::BTreeMap,
+  fmt,
+  future::Future,
+  panic::{self, AssertUnwindSafe},
+  sync::mpsc::{self, Receiver, Sender},
+  thread::{self, JoinHandle},
+};
+
+use crate::{
+  combinators::Running, scheduler::Handle, GeneratorResponse, StartedGenerator,
+  YieldWrapper,
+};
+
+/// A bunch of generators that all get queried together,
+/// spread across a few threads.
+///
+/// This is the multi-threaded counterpart of
+/// [`CoroutineSet`](crate::scheduler::CoroutineSet), without the messages,
+/// sleeping or families.
+pub struct ParallelSet<Q, Y, R> {
+  workers: Vec<Worker<Q, Y, R>>,
+  /// Which worker each running generator lives on
+  owners: BTreeMap<Handle, usize>,
+  finished: Vec<(Handle, R)>,
+  panicked: Vec<(Handle, Box<dyn Any + Send>)>,
+  next_handle: u64,
+}
+
+impl<Q, Y, R> ParallelSet<Q, Y, R>
+where
+  Q: Clone + Send + 'static,
+  Y: Send + 'static,
+  R: Send + 'static,
+{
+  /// Make a set with a worker thread for each core.
+  pub fn new() -> Self {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    Self::with_threads(threads)
+  }
+
+  /// Make a set with this many worker threads.
+  ///
+  /// Panics if `threads` is 0.
+  pub fn with_threads(threads: usize) -> Self {
+    assert!(threads > 0, "A ParallelSet needs at least one thread");
+    let workers = (0..threads)
+      .map(|i| {
+        let (commands, command_rx) = mpsc::channel();
+        let (reply_tx, replies) = mpsc::channel();
+        let thread = thread::Builder::new()
+          .name(format!("dedenne-worker-{}", i))
+          .spawn(move || work(command_rx, reply_tx))
+          .expect("couldn't spawn a worker thread");
+        Worker {
+          commands,
+          replies,
+          thread: Some(thread),
+          load: 0,
+        }
+      })
+      .collect();
+    Self {
+      workers,
+      owners: BTreeMap::new(),
+      finished: Vec::new(),
+      panicked: Vec::new(),
+      next_handle: 0,
+    }
+  }
+
+  /// Start a generator on whichever worker has the fewest,
+  /// and add it to the set.
+  ///
+  /// Like [`StartedGenerator::run`], this runs it up to its first yield,
+  /// which you get back here. If it returns or panics straight away you get
+  /// `None`, and its `R` or panic is collected like any other generator's.
+  pub fn spawn<F, Fut>(&mut self, f: F) -> (Handle, Option<Y>)
+  where
+    F: FnOnce(YieldWrapper<Q, Y>) -> Fut + Send + 'static,
+    Fut: Future<Output = R> + 'static,
+  {
+    let handle = Handle(self.next_handle);
+    self.next_handle += 1;
+    let (index, worker) = self
+      .workers
+      .iter_mut()
+      .enumerate()
+      .min_by_key(|(_, worker)| worker.load)
+      .expect("there's always at least one worker");
+    worker.send(Command::Spawn(
+      handle,
+      Box::new(move || StartedGenerator::run(f)),
+    ));
+    self.owners.insert(handle, index);
+    worker.load += 1;
+
+    let stepped = self.workers[index].recv();
+    let mut yields = self.collect(stepped);
+    (handle, yields.pop().map(|(_, yielded)| yielded))
+  }
+
+  /// Query every generator with a clone of `query`, in parallel,
+  /// and get back what each one yielded, in spawn order.
+  ///
+  /// Generators that return are removed from the set; get their `R`s from
+  /// [`take_finished`](Self::take_finished).
+  ///
+  /// If any generators panic, they're removed from the set too, and the
+  /// others' yields still come back as usual;
+  /// get the panics from [`take_panicked`](Self::take_panicked).
+  pub fn tick_all(&mut self, query: Q) -> Vec<(Handle, Y)> {
+    // Only bother the workers that have something to do
+    let busy = self
+      .workers
+      .iter()
+      .enumerate()
+      .filter(|(_, worker)| worker.load > 0)
+      .map(|(index, _)| index)
+      .collect::<Vec<_>>();
+    for &index in &busy {
+      self.workers[index].send(Command::Tick(query.clone()));
+    }
+    let mut stepped = Vec::with_capacity(self.owners.len());
+    for &index in &busy {
+      stepped.extend(self.workers[index].recv());
+    }
+    stepped.sort_by_key(|(handle, _)| *handle);
+    self.collect(stepped)
+  }
+
+  /// Stop a generator and drop it (on its own thread),
+  /// without collecting anything from it.
+  ///
+  /// Returns whether it was still running.
+  pub fn cancel(&mut self, handle: Handle) -> bool {
+    let Some(index) = self.owners.remove(&handle) else {
+      return false;
+    };
+    let worker = &mut self.workers[index];
+    worker.load -= 1;
+    worker.send(Command::Cancel(handle));
+    true
+  }
+
+  /// Take the `R`s of all the generators that have returned.
+  ///
+  /// Generators that returned on the same tick are in spawn order.
+  pub fn take_finished(&mut self) -> Vec<(Handle, R)> {
+    std::mem::take(&mut self.finished)
+  }
+
+  /// Take the panic payloads of all the generators that have panicked.
+  ///
+  /// Generators that panicked on the same tick are in spawn order.
+  /// Pass these to [`std::panic::resume_unwind`] if you'd rather they
+  /// weren't caught.
+  pub fn take_panicked(&mut self) -> Vec<(Handle, Box<dyn Any + Send>)> {
+    std::mem::take(&mut self.panicked)
+  }
+
+  /// Whether the generator is still in the set.
+  pub fn contains(&self, handle: Handle) -> bool {
+    self.owners.contains_key(&handle)
+  }
+
+  /// The handles of all the generators still running, in spawn order.
+  pub fn handles(&self) -> impl Iterator<Item = Handle> + '_ {
+    self.owners.keys().copied()
+  }
+
+  /// How many generators are still running.
+  pub fn len(&self) -> usize {
+    self.owners.len()
+  }
+
+  /// Whether no generators are still running.
+  pub fn is_empty(&self) -> bool {
+    self.owners.is_empty()
+  }
+
+  /// How many worker threads there are.
+  pub fn threads(&self) -> usize {
+    self.workers.len()
+  }
+
+  /// Sort out the results of stepping some generators (already in spawn
+  /// order), and hand back the yields.
+  fn collect(&mut self, stepped: Stepped<Y, R>) -> Vec<(Handle, Y)> {
+    let mut yields = Vec::with_capacity(stepped.len());
+    for (handle, resp) in stepped {
+      match resp {
+        Ok(GeneratorResponse::Yielding(yielded)) => {
+          yields.push((handle, yielded))
+        }
+        Ok(GeneratorResponse::Done(done)) => {
+          self.forget(handle);
+          self.finished.push((handle, done));
+        }
+        Err(payload) => {
+          self.forget(handle);
+          self.panicked.push((handle, payload));
+        }
+      }
+    }
+    yields
+  }
+
+  /// The worker's already dropped this generator; catch up with it.
+  fn forget(&mut self, handle: Handle) {
+    if let Some(index) = self.owners.remove(&handle) {
+      self.workers[index].load -= 1;
+    }
+  }
+}
+
+impl<Q, Y, R> Default for ParallelSet<Q, Y, R>
+where
+  Q: Clone + Send + 'static,
+  Y: Send + 'static,
+  R: Send + 'static,
+{
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<Q, Y, R> Drop for ParallelSet<Q, Y, R> {
+  fn drop(&mut self) {
+    // Hanging up tells the workers to drop their generators and stop
+    let threads = self
+      .workers
+      .iter_mut()
+      .filter_map(|worker| worker.thread.take())
+      .collect::<Vec<_>>();
+    self.workers.clear();
+    for thread in threads {
+      // Generators only panic while they're being stepped,
+      // and those are caught, so there's nothing useful in here
+      let _ = thread.join();
+    }
+  }
+}
+
+impl<Q, Y, R> fmt::Debug for ParallelSet<Q, Y, R> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ParallelSet")
+      .field("threads", &self.workers.len())
+      .field("running", &self.owners.keys().collect::<Vec<_>>())
+      .field("finished", &self.finished.len())
+      .field("panicked", &self.panicked.len())
+      .finish_non_exhaustive()
+  }
+}
+
+/// Starts a generator on the worker it's going to live on.
+type Starter<Q, Y, R> = Box<dyn FnOnce() -> Running<Y, R, Q> + Send>;
+
+/// How stepping each generator went, by handle.
+type Stepped<Y, R> = Vec<(Handle, thread::Result<GeneratorResponse<Y, R>>)>;
+
+enum Command<Q, Y, R> {
+  Spawn(Handle, Starter<Q, Y, R>),
+  Tick(Q),
+  Cancel(Handle),
+}
+
+struct Worker<Q, Y, R> {
+  commands: Sender<Command<Q, Y, R>>,
+  replies: Receiver<Stepped<Y, R>>,
+  thread: Option<JoinHandle<()>>,
+  /// How many generators live here
+  load: usize,
+}
+
+impl<Q, Y, R> Worker<Q, Y, R> {
+  fn send(&self, command: Command<Q, Y, R>) {
+    self
+      .commands
+      .send(command)
+      .unwrap_or_else(|_| panic!("A ParallelSet's worker thread died"))
+  }
+
+  fn recv(&self) -> Stepped<Y, R> {
+    self
+      .replies
+      .recv()
+      .unwrap_or_else(|_| panic!("A ParallelSet's worker thread died"))
+  }
+}
+
+/// What each worker thread does.
+fn work<Q: Clone, Y, R>(
+  commands: Receiver<Command<Q, Y, R>>,
+  replies: Sender<Stepped<Y, R>>,
+) {
+  let mut generators = BTreeMap::<Handle, StartedGenerator<Y, R, Q>>::new();
+  for command in commands {
+    let stepped = match command {
+      Command::Spawn(handle, start) => {
+        let resp = panic::catch_unwind(AssertUnwindSafe(start)).map(
+          |(generator, resp)| {
+            if let GeneratorResponse::Yielding(_) = resp {
+              generators.insert(handle, generator);
+            }
+            resp
+          },
+        );
+        vec![(handle, resp)]
+      }
+      Command::Tick(query) => {
+        let mut stepped = Vec::with_capacity(generators.len());
+        generators.retain(|&handle, generator| {
+          // A panicking `clone` counts against this generator too
+          let resp = panic::catch_unwind(AssertUnwindSafe(|| {
+            generator.query(query.clone())
+          }));
+          let keep = matches!(resp, Ok(GeneratorResponse::Yielding(_)));
+          stepped.push((handle, resp));
+          keep
+        });
+        stepped
+      }
+      Command::Cancel(handle) => {
+        generators.remove(&handle);
+        continue;
+      }
+    };
+    if replies.send(stepped).is_err() {
+      return;
+    }
+  }
+}
@@ -0,0 +1,1013 @@
+//! Running lots of generators side by side.
+//!
+//! A game might have hundreds of entity scripts, all of which want the
+//! same `query(dt)` every frame. A [`CoroutineSet`] owns them all and
+//! ticks them together, keyed by stable [`Handle`]s.
+//!
+//! ```rust
+//! # use dedenne::scheduler::CoroutineSet;
+//! let mut set: CoroutineSet<f64, _, _> = CoroutineSet::new();
+//! let (slow, _) = set.spawn(|y| async move {
+//!   let mut t = 0.0;
+//!   while t < 3.0 {
+//!     t += y.ield("slow").await;
+//!   }
+//!   "slow is done"
+//! });
+//! let (fast, _) = set.spawn(|y| async move {
+//!   let mut t = 0.0;
+//!   while t < 1.0 {
+//!     t += y.ield("fast").await;
+//!   }
+//!   "fast is done"
+//! });
+//!
+//! assert_eq!(set.tick_all(1.0), vec![(slow, "slow")]);
+//! assert_eq!(set.take_finished(), vec![(fast, "fast is done")]);
+//! assert_eq!(set.tick_all(1.0), vec![(slow, "slow")]);
+//! assert_eq!(set.tick_all(1.0), vec![]);
+//! assert_eq!(set.take_finished(), vec![(slow, "slow is done")]);
+//! assert!(set.is_empty());
+//! ```
+//!
+//! # Messages
+//!
+//! Coroutines in the same set can talk to each other.
+//! `y.send(handle, msg).await` puts a message in another coroutine's
+//! mailbox, and `y.recv().await` takes the next message out of your own,
+//! waiting for one if it's empty. A coroutine waiting in `recv` isn't
+//! queried by `tick_all`; it's woken the first time the set gets to it
+//! with mail waiting.
+//!
+//! Everything happens in a fixed order: coroutines run in spawn order and
+//! each mailbox is first-in first-out. So if a coroutine sends mail to
+//! one that was spawned after it, the recipient sees it the same tick;
+//! otherwise it sees it next tick.
+//!
+//! ```rust
+//! # use dedenne::scheduler::CoroutineSet;
+//! let mut set = CoroutineSet::<(), &str, (), &str>::new();
+//! let (door, _) = set.spawn(|y| async move {
+//!   loop {
+//!     let msg = y.recv().await;
+//!     y.ield(msg).await;
+//!   }
+//! });
+//! let (player, _) = set.spawn(move |y| async move {
+//!   y.ield("walking up to the door").await;
+//!   y.send(door, "door opened").await;
+//!   y.ield("opened it").await;
+//! });
+//!
+//! // The door is waiting for mail, so it's skipped
+//! assert_eq!(set.tick_all(()), vec![(player, "opened it")]);
+//! // It was spawned before the player, so it hears about it on the next tick
+//! assert_eq!(set.tick_all(()), vec![(door, "door opened")]);
+//! ```
+//!
+//! # Waiting
+//!
+//! Waiting in `recv` is one way for a coroutine to go to sleep. It can also
+//! wait for a [`Signal`] to be raised, for a particular frame
+//! (each `tick_all` is one frame), or for another coroutine to finish.
+//! Sleeping coroutines cost nothing per tick; `tick_all` only looks at the
+//! ones that are awake, and wakes the others when their time comes.
+//!
+//! ```rust
+//! # use dedenne::scheduler::CoroutineSet;
+//! let mut set = CoroutineSet::<(), &str, ()>::new();
+//! let alarm = set.new_signal();
+//! let (sleeper, _) = set.spawn(move |y| async move {
+//!   y.wait_signal(alarm).await;
+//!   y.ield("woke up").await;
+//! });
+//! let (napper, _) = set.spawn(|y| async move {
+//!   y.wait_until(3).await;
+//!   y.ield("nap over").await;
+//! });
+//! set.spawn(move |y| async move {
+//!   y.wait_for_child(napper).await;
+//!   y.raise(alarm);
+//! });
+//!
+//! assert_eq!(set.tick_all(()), vec![]);
+//! assert_eq!(set.tick_all(()), vec![]);
+//! assert_eq!(set.tick_all(()), vec![(napper, "nap over")]);
+//! // The napper finishes, so the alarm goes off. But the sleeper was spawned
+//! // first, so it's already had its turn this tick
+//! assert_eq!(set.tick_all(()), vec![]);
+//! assert_eq!(set.tick_all(()), vec![(sleeper, "woke up")]);
+//! ```
+//!
+//! # Families
+//!
+//! A coroutine can [`spawn`](Coroutine::spawn) children of its own, which
+//! are ticked by the set like everything else, and
+//! [`join`](Coroutine::join) them to find out how they ended.
+//! A child's `R` goes to its parent, not to
+//! [`take_finished`](CoroutineSet::take_finished), and if a child panics
+//! the parent gets the panic as an [`Exit::Panicked`], not
+//! [`take_panicked`](CoroutineSet::take_panicked).
+//!
+//! When a coroutine returns, panics or is cancelled, all of its children
+//! that are still running are cancelled first, youngest first
+//! (and each of their children before them, and so on).
+//!
+//! ```rust
+//! # use dedenne::scheduler::{CoroutineSet, Exit};
+//! let mut set = CoroutineSet::<(), &str, u32>::new();
+//! let (cutscene, _) = set.spawn(|y| async move {
+//!   let camera = y.spawn(|y| async move {
+//!     loop {
+//!       y.ield("panning").await;
+//!     }
+//!   });
+//!   let actor = y.spawn(|y| async move {
+//!     y.ield("walking").await;
+//!     y.ield("talking").await;
+//!     3
+//!   });
+//!   match y.join(actor).await {
+//!     Exit::Returned(lines) => lines,
+//!     _ => 0,
+//!   }
+//!   // Returning cancels the camera
+//! });
+//!
+//! assert_eq!(set.tick_all(()).len(), 2);
+//! assert_eq!(set.tick_all(()).len(), 2);
+//! // The actor returns, and the cutscene wakes up next tick
+//! assert_eq!(set.tick_all(()).len(), 1);
+//! assert_eq!(set.tick_all(()), vec![]);
+//! assert_eq!(set.take_finished(), vec![(cutscene, 3)]);
+//! assert!(set.is_empty());
+//! ```
+
+use std::{
+  any::Any,
+  cell::RefCell,
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+  fmt,
+  future::Future,
+  panic::{self, AssertUnwindSafe},
+  rc::Rc,
+};
+
+use crate::{
+  combinators::Running, GeneratorResponse, StartedGenerator, YieldPoint,
+  YieldWrapper,
+};
+
+/// Identifies a generator in a [`CoroutineSet`].
+///
+/// Handles are never reused, and they sort in the order
+/// their generators were spawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle(pub(crate) u64);
+
+/// Something coroutines in a [`CoroutineSet`] can wait for.
+///
+/// Make one with [`CoroutineSet::new_signal`] or [`Coroutine::new_signal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Signal(u64);
+
+/// How a child coroutine ended, as seen by its parent.
+#[derive(Debug)]
+pub enum Exit<R> {
+  /// It returned this.
+  Returned(R),
+  /// It was cancelled, by its parent or the set.
+  Cancelled,
+  /// It panicked with this payload.
+  Panicked(Box<dyn Any + Send>),
+}
+
+/// A bunch of generators that all get queried together.
+///
+/// Generators are always queried in the order they were spawned.
+/// When one returns, it's removed from the set and its `R` is kept
+/// until you [`take_finished`](Self::take_finished).
+/// Panics are caught and kept the same way, until you
+/// [`take_panicked`](Self::take_panicked), so one script going wrong
+/// doesn't take the whole tick down with it.
+///
+/// `M` is the type of [messages](self#messages) they can send each other.
+pub struct CoroutineSet<Q, Y, R, M = ()> {
+  running: HashMap<Handle, Entry<Q, Y, R>>,
+  /// The coroutines that are awake
+  ready: HashSet<Handle>,
+  /// The coroutines still to step this tick, soonest-spawned first.
+  /// Only awake ones ever go in here, so sleepers cost nothing.
+  queue: BinaryHeap<Reverse<Handle>>,
+  /// While ticking, the first handle that hasn't had its turn yet
+  cursor: Option<Handle>,
+  /// Coroutines waiting for a frame, soonest first
+  sleepers: BinaryHeap<Reverse<(u64, Handle)>>,
+  /// Coroutines waiting for another to finish, by the one they're waiting for
+  watchers: HashMap<Handle, Vec<Handle>>,
+  finished: Vec<(Handle, R)>,
+  panicked: Vec<(Handle, Box<dyn Any + Send>)>,
+  board: Board<Q, Y, R, M>,
+}
+
+impl<Q, Y, R, M> CoroutineSet<Q, Y, R, M> {
+  pub fn new() -> Self {
+    Self {
+      running: HashMap::new(),
+      ready: HashSet::new(),
+      queue: BinaryHeap::new(),
+      cursor: None,
+      sleepers: BinaryHeap::new(),
+      watchers: HashMap::new(),
+      finished: Vec::new(),
+      panicked: Vec::new(),
+      board: Rc::new(RefCell::new(BoardInner {
+        mailboxes: HashMap::new(),
+        receiving: HashSet::new(),
+        signals: HashMap::new(),
+        woken: Vec::new(),
+        spawned: Vec::new(),
+        cancelled: Vec::new(),
+        parents: HashMap::new(),
+        exits: HashMap::new(),
+        frame: 0,
+        next_signal: 0,
+        next_handle: 0,
+      })),
+    }
+  }
+
+  /// Start a coroutine and add it to the set.
+  ///
+  /// Like [`StartedGenerator::run`], this runs it up to its first yield,
+  /// which you get back here (it isn't part of the next `tick_all`).
+  /// If it returns or goes to sleep before yielding anything,
+  /// you get `None`. (If it returned or panicked, that's collected like
+  /// any other coroutine's.)
+  pub fn spawn<F, Fut>(&mut self, f: F) -> (Handle, Option<Y>)
+  where
+    F: FnOnce(Coroutine<Q, Y, R, M>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+    R: 'static,
+    M: 'static,
+  {
+    let (handle, starter) = starter(&self.board, f);
+    self.running.insert(handle, Entry::new(starter, None));
+    let first = self.step(handle, || unreachable!("starting doesn't query"));
+    self.sync();
+    (handle, first)
+  }
+
+  /// Add a plain generator that's already started.
+  ///
+  /// It can't send or receive messages, but it's ticked just the same.
+  pub fn insert(
+    &mut self,
+    (mut generator, mut resp): Running<Y, R, Q>,
+  ) -> (Handle, Option<Y>)
+  where
+    Q: 'static,
+    Y: 'static,
+    R: 'static,
+    M: 'static,
+  {
+    self.spawn(|y| async move {
+      loop {
+        match resp {
+          GeneratorResponse::Yielding(yielded) => {
+            let query = y.ield(yielded).await;
+            resp = generator.query(query);
+          }
+          GeneratorResponse::Done(done) => return done,
+        }
+      }
+    })
+  }
+
+  /// Move on to the next frame, and query every coroutine that's awake
+  /// with a clone of `query`. You get back what each one yielded.
+  ///
+  /// Coroutines that were asleep and whose wait is over are woken up
+  /// (instead of getting the query), and children spawned since the last
+  /// tick are started. Anything that wakes up or spawns a coroutine
+  /// later in spawn order than the one currently running takes effect this
+  /// tick; otherwise it takes effect next tick.
+  ///
+  /// Any coroutines that return are removed from the set;
+  /// get their `R`s from [`take_finished`](Self::take_finished).
+  ///
+  /// If a coroutine without a parent panics, it's removed from the set
+  /// (and its children cancelled) and the rest of the tick carries on;
+  /// get the panic from [`take_panicked`](Self::take_panicked).
+  pub fn tick_all(&mut self, query: Q) -> Vec<(Handle, Y)>
+  where
+    Q: Clone,
+  {
+    let frame = {
+      let mut board = self.board.borrow_mut();
+      board.frame += 1;
+      board.frame
+    };
+    while let Some(&Reverse((wake_at, handle))) = self.sleepers.peek() {
+      if wake_at > frame {
+        break;
+      }
+      self.sleepers.pop();
+      // Skip it if it's been cancelled since it went to sleep
+      let still_waiting = self.running.get(&handle).is_some_and(|entry| {
+        matches!(entry.waiting, Some(Wait::Frame(frame)) if frame == wake_at)
+      });
+      if still_waiting {
+        self.board.borrow_mut().woken.push(handle);
+      }
+    }
+
+    let mut yields = Vec::with_capacity(self.ready.len());
+    self.queue = self.ready.iter().copied().map(Reverse).collect();
+    self.cursor = Some(Handle(0));
+    loop {
+      self.sync();
+      let Some(Reverse(handle)) = self.queue.pop() else {
+        break;
+      };
+      // It might have gone to sleep or been cancelled since it was queued,
+      // or been queued twice
+      if Some(handle) < self.cursor || !self.ready.contains(&handle) {
+        continue;
+      }
+      self.cursor = Some(Handle(handle.0 + 1));
+      if let Some(yielded) = self.step(handle, || query.clone()) {
+        yields.push((handle, yielded));
+      }
+    }
+    self.cursor = None;
+    yields
+  }
+
+  /// Query just one coroutine (or wake it up, as in `tick_all`).
+  ///
+  /// Returns `None` if it isn't in the set, is asleep,
+  /// or didn't yield anything.
+  /// If it returns or panics, it's removed and that's collected as usual.
+  pub fn tick(&mut self, handle: Handle, query: Q) -> Option<Y> {
+    self.sync();
+    let out = if self.ready.contains(&handle) {
+      self.step(handle, || query)
+    } else {
+      None
+    };
+    self.sync();
+    out
+  }
+
+  /// Stop a coroutine and drop it, without collecting anything from it.
+  /// Any mail waiting for it is dropped too.
+  ///
+  /// Its children are cancelled first, and if it has a parent,
+  /// the parent gets [`Exit::Cancelled`].
+  ///
+  /// Returns whether it was still running.
+  pub fn cancel(&mut self, handle: Handle) -> bool {
+    let out = self.retire(handle, Exit::Cancelled);
+    self.sync();
+    out
+  }
+
+  /// Put a message in a coroutine's mailbox from outside the set.
+  ///
+  /// Returns `false` (dropping the message) if it isn't running.
+  pub fn send(&self, to: Handle, msg: M) -> bool {
+    self.board.borrow_mut().deliver(to, msg)
+  }
+
+  /// Make a new signal for coroutines in this set to wait on.
+  pub fn new_signal(&self) -> Signal {
+    self.board.borrow_mut().new_signal()
+  }
+
+  /// Wake up everything waiting on `signal`.
+  pub fn raise(&self, signal: Signal) {
+    self.board.borrow_mut().raise(signal)
+  }
+
+  /// How many times `tick_all` has been called.
+  pub fn frame(&self) -> u64 {
+    self.board.borrow().frame
+  }
+
+  /// Take the `R`s of all the coroutines that have returned,
+  /// in the order they returned.
+  ///
+  /// This doesn't include children; their `R`s go to their parents.
+  pub fn take_finished(&mut self) -> Vec<(Handle, R)> {
+    std::mem::take(&mut self.finished)
+  }
+
+  /// Take the panic payloads of all the coroutines that have panicked,
+  /// in the order they panicked.
+  ///
+  /// Like `take_finished`, this doesn't include children. Pass these to
+  /// [`std::panic::resume_unwind`] if you'd rather they weren't caught.
+  pub fn take_panicked(&mut self) -> Vec<(Handle, Box<dyn Any + Send>)> {
+    std::mem::take(&mut self.panicked)
+  }
+
+  /// Whether the coroutine is still in the set.
+  pub fn contains(&self, handle: Handle) -> bool {
+    self.running.contains_key(&handle)
+  }
+
+  /// The coroutine that spawned this one, if any.
+  pub fn parent(&self, handle: Handle) -> Option<Handle> {
+    self.running.get(&handle)?.parent
+  }
+
+  /// Whether the coroutine is asleep, for any reason.
+  ///
+  /// Once what it's waiting for has happened, it's awake,
+  /// even if it hasn't been stepped yet.
+  pub fn is_asleep(&self, handle: Handle) -> bool {
+    self.sleeping_on(handle).is_some()
+  }
+
+  /// Whether the coroutine is waiting in `recv` for mail.
+  pub fn is_receiving(&self, handle: Handle) -> bool {
+    matches!(self.sleeping_on(handle), Some(Wait::Mail))
+  }
+
+  /// The handles of all the coroutines still running, in spawn order.
+  pub fn handles(&self) -> impl Iterator<Item = Handle> + '_ {
+    let mut handles = self.running.keys().copied().collect::<Vec<_>>();
+    handles.sort_unstable();
+    handles.into_iter()
+  }
+
+  /// How many coroutines are still running.
+  pub fn len(&self) -> usize {
+    self.running.len()
+  }
+
+  /// How many coroutines are running and not asleep.
+  pub fn awake_len(&self) -> usize {
+    self.ready.len()
+  }
+
+  /// Whether no coroutines are still running.
+  pub fn is_empty(&self) -> bool {
+    self.running.is_empty()
+  }
+
+  /// What the coroutine's waiting for, if it's still waiting.
+  fn sleeping_on(&self, handle: Handle) -> Option<Wait> {
+    let wait = self.running.get(&handle)?.waiting?;
+    let woken = self.ready.contains(&handle)
+      || self.board.borrow().woken.contains(&handle);
+    (!woken).then_some(wait)
+  }
+
+  /// Start, query or wake a coroutine that's in `ready`.
+  fn step(&mut self, handle: Handle, query: impl FnOnce() -> Q) -> Option<Y> {
+    let entry = self
+      .running
+      .get_mut(&handle)
+      .expect("ready coroutines are always running");
+    let resp = panic::catch_unwind(AssertUnwindSafe(|| {
+      match (entry.starter.take(), &mut entry.generator) {
+        (Some(start), _) => {
+          let (generator, resp) = start();
+          entry.generator = Some(generator);
+          resp
+        }
+        (None, Some(generator)) => {
+          let wake = match entry.waiting.take() {
+            Some(_) => Wake::Woken,
+            None => Wake::Query(query()),
+          };
+          generator.query(wake)
+        }
+        (None, None) => unreachable!("coroutine was neither started nor not"),
+      }
+    }));
+
+    let wait = match resp {
+      Ok(GeneratorResponse::Yielding(Suspend::Yield(yielded))) => {
+        self.wake(handle);
+        return Some(yielded);
+      }
+      Ok(GeneratorResponse::Yielding(Suspend::Wait(wait))) => wait,
+      Ok(GeneratorResponse::Done(done)) => {
+        self.retire(handle, Exit::Returned(done));
+        return None;
+      }
+      Err(payload) => {
+        self.retire(handle, Exit::Panicked(payload));
+        return None;
+      }
+    };
+
+    self.ready.remove(&handle);
+    if let Some(entry) = self.running.get_mut(&handle) {
+      entry.waiting = Some(wait);
+    }
+    let mut board = self.board.borrow_mut();
+    match wait {
+      Wait::Mail => {
+        board.receiving.insert(handle);
+      }
+      Wait::Signal(signal) => {
+        board.signals.entry(signal).or_default().push(handle);
+      }
+      Wait::Frame(frame) => {
+        self.sleepers.push(Reverse((frame, handle)));
+      }
+      Wait::Finished(other) => {
+        self.watchers.entry(other).or_default().push(handle);
+      }
+    }
+    None
+  }
+
+  /// Remove a coroutine from the set, after cancelling its children,
+  /// and tell whoever needs to know how it ended.
+  ///
+  /// Returns whether it was running.
+  fn retire(&mut self, handle: Handle, exit: Exit<R>) -> bool {
+    let Some(entry) = self.running.remove(&handle) else {
+      return false;
+    };
+    self.ready.remove(&handle);
+    for &child in entry.children.iter().rev() {
+      self.retire(child, Exit::Cancelled);
+    }
+    // Drop the body (if it hasn't finished already) after its children,
+    // and without the board borrowed, in case something in it cares
+    let Entry {
+      starter,
+      generator,
+      waiting,
+      parent,
+      ..
+    } = entry;
+    drop((starter, generator));
+
+    // Stop waiting for whatever it was waiting for. (Frame sleepers are
+    // skipped when their frame comes.)
+    match waiting {
+      Some(Wait::Signal(signal)) => {
+        let mut board = self.board.borrow_mut();
+        if let Some(waiting) = board.signals.get_mut(&signal) {
+          waiting.retain(|&waiter| waiter != handle);
+          if waiting.is_empty() {
+            board.signals.remove(&signal);
+          }
+        }
+      }
+      Some(Wait::Finished(other)) => {
+        if let Some(watchers) = self.watchers.get_mut(&other) {
+          watchers.retain(|&watcher| watcher != handle);
+          if watchers.is_empty() {
+            self.watchers.remove(&other);
+          }
+        }
+      }
+      Some(Wait::Mail | Wait::Frame(_)) | None => {}
+    }
+
+    let mut board = self.board.borrow_mut();
+    // Its children's exits aren't any use to anyone now
+    board.exits.remove(&handle);
+    board.mailboxes.remove(&handle);
+    board.receiving.remove(&handle);
+    board.parents.remove(&handle);
+    if let Some(watchers) = self.watchers.remove(&handle) {
+      board.woken.extend(watchers);
+    }
+
+    match parent
+      .and_then(|parent| Some((parent, self.running.get_mut(&parent)?)))
+    {
+      Some((parent, parent_entry)) => {
+        parent_entry.children.retain(|&child| child != handle);
+        board.exits.entry(parent).or_default().insert(handle, exit);
+      }
+      _ => {
+        drop(board);
+        match exit {
+          Exit::Returned(done) => self.finished.push((handle, done)),
+          Exit::Cancelled => {}
+          Exit::Panicked(payload) => self.panicked.push((handle, payload)),
+        }
+      }
+    }
+    true
+  }
+
+  /// Catch up on everything the coroutines asked for while they were
+  /// running: start new children, cancel the ones they cancelled,
+  /// and move everything that's been woken up into `ready`.
+  fn sync(&mut self) {
+    loop {
+      let (spawned, cancelled, woken) = {
+        let mut board = self.board.borrow_mut();
+        (
+          std::mem::take(&mut board.spawned),
+          std::mem::take(&mut board.cancelled),
+          std::mem::take(&mut board.woken),
+        )
+      };
+      if spawned.is_empty() && cancelled.is_empty() && woken.is_empty() {
+        return;
+      }
+
+      for (handle, parent, starter) in spawned {
+        match self.running.get_mut(&parent) {
+          Some(parent_entry) => {
+            parent_entry.children.push(handle);
+            self
+              .running
+              .insert(handle, Entry::new(starter, Some(parent)));
+            self.wake(handle);
+          }
+          // The parent finished before the child ever got going
+          None => {
+            let mut board = self.board.borrow_mut();
+            board.mailboxes.remove(&handle);
+            board.parents.remove(&handle);
+            if let Some(watchers) = self.watchers.remove(&handle) {
+              board.woken.extend(watchers);
+            }
+          }
+        }
+      }
+      for handle in cancelled {
+        self.retire(handle, Exit::Cancelled);
+      }
+      for handle in woken {
+        // It might have been cancelled since
+        let asleep = self
+          .running
+          .get(&handle)
+          .is_some_and(|entry| entry.waiting.is_some());
+        if asleep {
+          self.wake(handle);
+        }
+      }
+    }
+  }
+
+  /// Mark a coroutine as awake. If we're partway through a tick and it
+  /// hasn't had its turn yet, it gets one this tick.
+  fn wake(&mut self, handle: Handle) {
+    self.ready.insert(handle);
+    if self.cursor.is_some_and(|cursor| handle >= cursor) {
+      self.queue.push(Reverse(handle));
+    }
+  }
+}
+
+impl<Q, Y, R, M> Default for CoroutineSet<Q, Y, R, M> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<Q, Y, R, M> Drop for CoroutineSet<Q, Y, R, M> {
+  fn drop(&mut self) {
+    // Children that never got started hold on to the board,
+    // so get rid of them or it'd never be freed
+    let spawned = std::mem::take(&mut self.board.borrow_mut().spawned);
+    drop(spawned);
+  }
+}
+
+impl<Q, Y, R, M> fmt::Debug for CoroutineSet<Q, Y, R, M> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CoroutineSet")
+      .field("running", &self.handles().collect::<Vec<_>>())
+      .field("awake", &self.ready.len())
+      .field("finished", &self.finished.len())
+      .field("panicked", &self.panicked.len())
+      .finish_non_exhaustive()
+  }
+}
+
+/// The type of `y` in the body of a coroutine in a [`CoroutineSet`].
+pub struct Coroutine<Q, Y, R, M = ()> {
+  y: YieldWrapper<Wake<Q>, Suspend<Y>>,
+  me: Handle,
+  board: Board<Q, Y, R, M>,
+}
+
+impl<Q, Y, R, M> Coroutine<Q, Y, R, M> {
+  /// Yield to the set, just like [`YieldWrapper::ield`].
+  #[track_caller]
+  pub fn ield(&self, yielded: Y) -> impl Future<Output = Q> {
+    let fut = self.y.ield(Suspend::Yield(yielded));
+    async move {
+      match fut.await {
+        Wake::Query(query) => query,
+        Wake::Woken => unreachable!("Woke up a coroutine that was yielding"),
+      }
+    }
+  }
+
+  /// This coroutine's own handle.
+  pub fn me(&self) -> Handle {
+    self.me
+  }
+
+  /// Put a message in another coroutine's mailbox.
+  ///
+  /// This never actually suspends.
+  /// Returns `false` (dropping the message) if that coroutine isn't running.
+  pub async fn send(&self, to: Handle, msg: M) -> bool {
+    self.board.borrow_mut().deliver(to, msg)
+  }
+
+  /// Take the next message out of this coroutine's mailbox,
+  /// sleeping until one arrives if it's empty.
+  #[track_caller]
+  pub fn recv(&self) -> impl Future<Output = M> + '_ {
+    let point = YieldPoint::here(Some("recv"));
+    async move {
+      loop {
+        if let Some(msg) = self.try_recv() {
+          return msg;
+        }
+        self.sleep(point, Wait::Mail).await;
+      }
+    }
+  }
+
+  /// Take the next message out of this coroutine's mailbox,
+  /// if there is one.
+  pub fn try_recv(&self) -> Option<M> {
+    self
+      .board
+      .borrow_mut()
+      .mailboxes
+      .get_mut(&self.me)?
+      .pop_front()
+  }
+
+  /// Make a new signal for coroutines in this set to wait on.
+  pub fn new_signal(&self) -> Signal {
+    self.board.borrow_mut().new_signal()
+  }
+
+  /// Wake up everything waiting on `signal`.
+  ///
+  /// This doesn't suspend.
+  pub fn raise(&self, signal: Signal) {
+    self.board.borrow_mut().raise(signal)
+  }
+
+  /// Sleep until someone raises `signal`.
+  #[track_caller]
+  pub fn wait_signal(&self, signal: Signal) -> impl Future<Output = ()> + '_ {
+    let point = YieldPoint::here(Some("wait_signal"));
+    self.sleep(point, Wait::Signal(signal))
+  }
+
+  /// The current frame; that is, how many times `tick_all` has been called.
+  pub fn frame(&self) -> u64 {
+    self.board.borrow().frame
+  }
+
+  /// Sleep until `tick_all` gets to `frame`.
+  ///
+  /// Returns straight away if it's already that frame or later.
+  #[track_caller]
+  pub fn wait_until(&self, frame: u64) -> impl Future<Output = ()> + '_ {
+    let point = YieldPoint::here(Some("wait_until"));
+    async move {
+      if self.frame() < frame {
+        self.sleep(point, Wait::Frame(frame)).await;
+      }
+    }
+  }
+
+  /// Sleep until the coroutine `other` returns or is cancelled.
+  ///
+  /// Returns straight away if it isn't running.
+  /// To find out how one of your own children ended, use `join` instead.
+  #[track_caller]
+  pub fn wait_for_child(&self, other: Handle) -> impl Future<Output = ()> + '_ {
+    let point = YieldPoint::here(Some("wait_for_child"));
+    async move {
+      let running = self.board.borrow().mailboxes.contains_key(&other);
+      if running {
+        self.sleep(point, Wait::Finished(other)).await;
+      }
+    }
+  }
+
+  /// Spawn a child coroutine.
+  ///
+  /// It starts running the next time the set gets to it: later this tick
+  /// if it's `tick_all`, since children always come after their parents.
+  /// Its first yield is reported by `tick_all` like any other.
+  pub fn spawn<F, Fut>(&self, f: F) -> Handle
+  where
+    F: FnOnce(Coroutine<Q, Y, R, M>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    Q: 'static,
+    Y: 'static,
+    R: 'static,
+    M: 'static,
+  {
+    let (handle, starter) = starter(&self.board, f);
+    let mut board = self.board.borrow_mut();
+    board.parents.insert(handle, self.me);
+    board.spawned.push((handle, self.me, starter));
+    handle
+  }
+
+  /// Sleep until one of this coroutine's children ends,
+  /// and find out how it did.
+  ///
+  /// Panics if `child` isn't a child of this coroutine,
+  /// or it's already been joined.
+  #[track_caller]
+  pub fn join(&self, child: Handle) -> impl Future<Output = Exit<R>> + '_ {
+    let point = YieldPoint::here(Some("join"));
+    async move {
+      loop {
+        {
+          let mut board = self.board.borrow_mut();
+          let mine = board.exits.get_mut(&self.me);
+          if let Some(exit) = mine.and_then(|exits| exits.remove(&child)) {
+            return exit;
+          }
+          if board.parents.get(&child) != Some(&self.me) {
+            panic!(
+              "Tried to join {:?}, which isn't a child of {:?} \
+              (or was already joined)",
+              child, self.me
+            )
+          }
+        }
+        self.sleep(point, Wait::Finished(child)).await;
+      }
+    }
+  }
+
+  /// Cancel one of this coroutine's children (and its children, and so on).
+  /// `join`ing it afterwards gives [`Exit::Cancelled`].
+  ///
+  /// This takes effect as soon as this coroutine next suspends.
+  /// Returns `false` if `child` isn't a running child of this coroutine.
+  pub fn cancel(&self, child: Handle) -> bool {
+    let mut board = self.board.borrow_mut();
+    if board.parents.get(&child) == Some(&self.me) {
+      board.cancelled.push(child);
+      true
+    } else {
+      false
+    }
+  }
+
+  async fn sleep(&self, point: YieldPoint, wait: Wait) {
+    match self.y.ield_at(point, Suspend::Wait(wait)).await {
+      Wake::Woken => {}
+      Wake::Query(_) => unreachable!("Queried a coroutine that was asleep"),
+    }
+  }
+}
+
+/// Makes a new coroutine's generator, running it to its first yield.
+type Starter<Q, Y, R> = Box<dyn FnOnce() -> Running<Suspend<Y>, R, Wake<Q>>>;
+
+/// Pick a handle for a new coroutine and get ready to start it.
+fn starter<Q, Y, R, M, F, Fut>(
+  board: &Board<Q, Y, R, M>,
+  f: F,
+) -> (Handle, Starter<Q, Y, R>)
+where
+  F: FnOnce(Coroutine<Q, Y, R, M>) -> Fut + 'static,
+  Fut: Future<Output = R> + 'static,
+  Q: 'static,
+  Y: 'static,
+  R: 'static,
+  M: 'static,
+{
+  let handle = {
+    let mut board = board.borrow_mut();
+    let handle = Handle(board.next_handle);
+    board.next_handle += 1;
+    board.mailboxes.insert(handle, VecDeque::new());
+    handle
+  };
+  let board = board.clone();
+  let starter = Box::new(move || {
+    StartedGenerator::run(move |y| {
+      f(Coroutine {
+        y,
+        me: handle,
+        board,
+      })
+    })
+  });
+  (handle, starter)
+}
+
+/// State shared between a set and its coroutines.
+type Board<Q, Y, R, M> = Rc<RefCell<BoardInner<Q, Y, R, M>>>;
+
+struct BoardInner<Q, Y, R, M> {
+  /// Everyone's mailboxes. Only running coroutines have one.
+  mailboxes: HashMap<Handle, VecDeque<M>>,
+  /// Coroutines sleeping in `recv`
+  receiving: HashSet<Handle>,
+  /// Coroutines waiting for a signal, by signal
+  signals: HashMap<Signal, Vec<Handle>>,
+  /// Coroutines that need moving into `ready`
+  woken: Vec<Handle>,
+  /// Children that need adding to the set, and their parents
+  spawned: Vec<(Handle, Handle, Starter<Q, Y, R>)>,
+  /// Children their parents have cancelled
+  cancelled: Vec<Handle>,
+  /// Running children's parents
+  parents: HashMap<Handle, Handle>,
+  /// How children ended, by parent then child,
+  /// until the parents `join` them or end themselves
+  exits: HashMap<Handle, HashMap<Handle, Exit<R>>>,
+  frame: u64,
+  next_signal: u64,
+  next_handle: u64,
+}
+
+impl<Q, Y, R, M> BoardInner<Q, Y, R, M> {
+  fn deliver(&mut self, to: Handle, msg: M) -> bool {
+    let Some(mailbox) = self.mailboxes.get_mut(&to) else {
+      return false;
+    };
+    mailbox.push_back(msg);
+    if self.receiving.remove(&to) {
+      self.woken.push(to);
+    }
+    true
+  }
+
+  fn new_signal(&mut self) -> Signal {
+    let signal = Signal(self.next_signal);
+    self.next_signal += 1;
+    signal
+  }
+
+  fn raise(&mut self, signal: Signal) {
+    if let Some(waiting) = self.signals.remove(&signal) {
+      self.woken.extend(waiting);
+    }
+  }
+}
+
+/// Why a coroutine suspended.
+enum Suspend<Y> {
+  Yield(Y),
+  Wait(Wait),
+}
+
+/// What a sleeping coroutine is waiting for.
+#[derive(Clone, Copy)]
+enum Wait {
+  Mail,
+  Signal(Signal),
+  Frame(u64),
+  Finished(Handle),
+}
+
+/// Why a coroutine was resumed.
+enum Wake<Q> {
+  Query(Q),
+  /// It was asleep, and what it was waiting for happened
+  Woken,
+}
+
+/// A coroutine that's still running.
+struct Entry<Q, Y, R> {
+  /// Only `Some` until it's first stepped
+  starter: Option<Starter<Q, Y, R>>,
+  generator: Option<StartedGenerator<Suspend<Y>, R, Wake<Q>>>,
+  /// What it's waiting for, if anything (even if it's been woken up
+  /// and just hasn't been stepped yet)
+  waiting: Option<Wait>,
+  parent: Option<Handle>,
+  /// The ones still running, in spawn order
+  children: Vec<Handle>,
+}
+
+impl<Q, Y, R> Entry<Q, Y, R> {
+  fn new(starter: Starter<Q, Y, R>, parent: Option<Handle>) -> Self {
+    Self {
+      starter: Some(starter),
+      generator: None,
+      waiting: None,
+      parent,
+      children: Vec::new(),
+    }
+  }
+}
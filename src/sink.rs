@@ -0,0 +1,204 @@
+//! Generators as consumers.
+//!
+//! Some generators are parsers or accumulators: they take in lots of queries
+//! and only really care about giving you their `R` at the end.
+//! A [`Sink`] lets you feed them with `Extend`, [`fold`](Sink::fold)
+//! and friends.
+//!
+//! A sink's generator takes `Option<T>` queries; `None` means end of input
+//! (same as the downstream end of [`then`](crate::GeneratorExt::then)).
+//! Whatever it yields in between is treated as an acknowledgement,
+//! which you can look at with [`Sink::ack`] or ignore.
+
+use std::{error::Error, fmt, future::Future};
+
+use crate::{
+  combinators::Running, GeneratorResponse, StartedGenerator, YieldWrapper,
+};
+
+/// Feed a generator items until it returns.
+pub struct Sink<Y, R, T> {
+  state: SinkState<Y, R, T>,
+  last_ack: Option<Y>,
+}
+
+impl<Y, R, T> Sink<Y, R, T> {
+  /// Wrap a generator that's already started.
+  pub fn new((generator, resp): Running<Y, R, Option<T>>) -> Self {
+    let mut out = Self {
+      state: SinkState::Feeding(generator),
+      last_ack: None,
+    };
+    out.handle(resp);
+    out
+  }
+
+  /// Start a generator and wrap it.
+  pub fn run<F, Fut>(f: F) -> Self
+  where
+    F: FnOnce(YieldWrapper<Option<T>, Y>) -> Fut + 'static,
+    Fut: Future<Output = R> + 'static,
+    T: 'static,
+    Y: 'static,
+  {
+    Self::new(StartedGenerator::run(f))
+  }
+
+  /// Feed one item to the generator.
+  ///
+  /// Returns `false` if the generator has already returned,
+  /// in which case the item is dropped.
+  pub fn feed(&mut self, item: T) -> bool {
+    match &mut self.state {
+      SinkState::Feeding(generator) => {
+        let resp = generator.query(Some(item));
+        self.handle(resp);
+        true
+      }
+      SinkState::Done(_) | SinkState::Spent => false,
+    }
+  }
+
+  /// Feed items to the generator until it returns or the items run out.
+  ///
+  /// If the generator has returned (now or before), you get its `R`,
+  /// and the sink is spent: there's nothing left to
+  /// [`finish`](Self::finish).
+  /// If you want to keep the items it didn't need, pass `&mut iter`;
+  /// otherwise they're dropped along with the iterator.
+  ///
+  /// Panics if the `R` was already taken.
+  pub fn feed_all<I>(&mut self, iter: I) -> Result<R, NeedMore>
+  where
+    I: IntoIterator<Item = T>,
+  {
+    let mut iter = iter.into_iter();
+    while !self.is_done() {
+      match iter.next() {
+        Some(item) => {
+          self.feed(item);
+        }
+        None => break,
+      }
+    }
+    match std::mem::replace(&mut self.state, SinkState::Spent) {
+      SinkState::Done(done) => Ok(done),
+      SinkState::Spent => panic!("the sink's response was already taken"),
+      feeding => {
+        self.state = feeding;
+        Err(NeedMore)
+      }
+    }
+  }
+
+  /// Feed the generator every item, then [`finish`](Self::finish) it.
+  ///
+  /// This is the generator version of [`Iterator::fold`]: the generator
+  /// body is the accumulator, and its `R` is the result.
+  /// If it returns before the items run out, the rest aren't used.
+  pub fn fold<I>(mut self, iter: I) -> R
+  where
+    I: IntoIterator<Item = T>,
+  {
+    match self.feed_all(iter) {
+      Ok(done) => done,
+      Err(NeedMore) => self.finish(),
+    }
+  }
+
+  /// Tell the generator there's no more input and get its `R`.
+  ///
+  /// This keeps querying with `None` until the generator returns.
+  ///
+  /// Panics if `feed_all` already took the `R`.
+  pub fn finish(mut self) -> R {
+    loop {
+      match self.state {
+        SinkState::Feeding(ref mut generator) => {
+          let resp = generator.query(None);
+          self.handle(resp);
+        }
+        SinkState::Done(done) => return done,
+        SinkState::Spent => {
+          panic!("the sink's response was already taken")
+        }
+      }
+    }
+  }
+
+  /// Whether the generator has returned (even if `feed_all` took its `R`).
+  pub fn is_done(&self) -> bool {
+    !matches!(self.state, SinkState::Feeding(_))
+  }
+
+  /// Peek at the response, if the generator has returned.
+  pub fn response(&self) -> Option<&R> {
+    match &self.state {
+      SinkState::Done(done) => Some(done),
+      _ => None,
+    }
+  }
+
+  /// The last thing the generator yielded.
+  pub fn ack(&self) -> Option<&Y> {
+    self.last_ack.as_ref()
+  }
+
+  /// Take the last thing the generator yielded.
+  pub fn take_ack(&mut self) -> Option<Y> {
+    self.last_ack.take()
+  }
+
+  fn handle(&mut self, resp: GeneratorResponse<Y, R>) {
+    match resp {
+      GeneratorResponse::Yielding(ack) => self.last_ack = Some(ack),
+      GeneratorResponse::Done(done) => self.state = SinkState::Done(done),
+    }
+  }
+}
+
+impl<Y, R, T> Extend<T> for Sink<Y, R, T> {
+  /// Feed items until the generator returns.
+  /// Anything after that gets dropped.
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    for item in iter {
+      if !self.feed(item) {
+        break;
+      }
+    }
+  }
+}
+
+impl<Y, R, T> fmt::Debug for Sink<Y, R, T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let state = match &self.state {
+      SinkState::Feeding(..) => "Feeding",
+      SinkState::Done(..) => "Done",
+      SinkState::Spent => "Spent",
+    };
+    f.debug_struct("Sink")
+      .field("state", &state)
+      .finish_non_exhaustive()
+  }
+}
+
+/// Returned by [`Sink::feed_all`] when the input ran out before
+/// the generator returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeedMore;
+
+impl fmt::Display for NeedMore {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "the generator needs more input before it can return")
+  }
+}
+
+impl Error for NeedMore {}
+
+/// Tracks the state of a `Sink`
+enum SinkState<Y, R, T> {
+  Feeding(StartedGenerator<Y, R, Option<T>>),
+  Done(R),
+  /// `feed_all` took the `R`
+  Spent,
+}
@@ -0,0 +1,162 @@
+//! Waiting for things in generators that are queried with the frame time.
+//!
+//! A lot of game scripts are generators with `Q = f32`, queried once a
+//! frame with how many seconds the frame took. The helpers here save
+//! writing out the same accumulator loop around `y.ield` every time.
+//!
+//! A wait has to yield something every frame, and all it knows about `Y`
+//! is the type, so it yields `Y::default()`. That's why these need
+//! `Y: Default`; scripts usually yield `Option`s, so waiting yields `None`.
+//!
+//! Frames never line up exactly with the times you ask for, so a wait
+//! usually finishes a little late. The helpers give back how late
+//! (the *overshoot*), and `y` also remembers it, so the next timed
+//! wait starts out that far along. That way `wait_secs(1.0)` twice in a row
+//! finishes as close to 2 seconds in as the frames allow, instead of
+//! drifting later and later.
+//!
+//! ```rust
+//! # use dedenne::*;
+//! let (mut gen, _) = StartedGenerator::run(|y| async move {
+//!   let late = y.wait_secs(1.0).await;
+//!   y.ield(Some(late)).await;
+//!   // This only needs another 0.8 seconds, thanks to the leftover 0.2
+//!   y.wait_secs(1.0).await;
+//!   y.ield(Some(0.0)).await;
+//! });
+//! // It yields `None` while it's waiting
+//! let mut frame = 0;
+//! let mut said = Vec::new();
+//! while said.len() < 2 {
+//!   frame += 1;
+//!   if let GeneratorResponse::Yielding(Some(late)) = gen.query(0.3) {
+//!     said.push((frame, (late * 10.0).round()));
+//!   }
+//! }
+//! assert_eq!(said, [(4, 2.0), (8, 0.0)]);
+//! ```
+//!
+//! Plain `y.ield`s and [`wait_frames`](YieldWrapper::wait_frames) don't
+//! touch the leftover time; only the timed waits (and the
+//! [`tween`](crate::tween)s) use it up.
+//! Call [`forget_leftover`](YieldWrapper::forget_leftover)
+//! if you want to start from scratch.
+
+use std::future::Future;
+
+use crate::{YieldPoint, YieldWrapper};
+
+impl<Y> YieldWrapper<f32, Y> {
+  /// How far past the end the last timed wait finished,
+  /// which the next one will count as already gone by.
+  pub fn leftover(&self) -> f32 {
+    self.carry.get()
+  }
+
+  /// Throw away the leftover time, so the next timed wait starts from 0.
+  pub fn forget_leftover(&self) {
+    self.carry.set(0.0);
+  }
+}
+
+impl<Y: Default> YieldWrapper<f32, Y> {
+  /// Wait until `secs` seconds have gone by.
+  ///
+  /// Returns how long past `secs` it actually was.
+  /// If the leftover time from the last timed wait is already more than
+  /// `secs`, this returns without yielding at all.
+  #[track_caller]
+  pub fn wait_secs(&self, secs: f32) -> impl Future<Output = f32> + '_ {
+    let point = YieldPoint::here(Some("wait_secs"));
+    async move {
+      let mut elapsed = self.carry.take();
+      while elapsed < secs {
+        elapsed += self.ield_at(point, Y::default()).await;
+      }
+      let overshoot = elapsed - secs;
+      self.carry.set(overshoot);
+      overshoot
+    }
+  }
+
+  /// Wait for `frames` queries, however long they take.
+  ///
+  /// Returns how many seconds went by.
+  #[track_caller]
+  pub fn wait_frames(&self, frames: u32) -> impl Future<Output = f32> + '_ {
+    let point = YieldPoint::here(Some("wait_frames"));
+    async move {
+      let mut elapsed = 0.0;
+      for _ in 0..frames {
+        elapsed += self.ield_at(point, Y::default()).await;
+      }
+      elapsed
+    }
+  }
+
+  /// Wait until `done` returns `true`.
+  ///
+  /// It's given how many seconds have gone by since the wait started,
+  /// and checked once before yielding and then once every frame.
+  /// Returns how many seconds went by.
+  ///
+  /// There's no target time to overshoot, so this uses up any
+  /// leftover time and leaves none behind.
+  #[track_caller]
+  pub fn wait_until<'b, F>(
+    &'b self,
+    mut done: F,
+  ) -> impl Future<Output = f32> + 'b
+  where
+    F: FnMut(f32) -> bool + 'b,
+  {
+    let point = YieldPoint::here(Some("wait_until"));
+    async move {
+      let mut elapsed = self.carry.take();
+      while !done(elapsed) {
+        elapsed += self.ield_at(point, Y::default()).await;
+      }
+      elapsed
+    }
+  }
+
+  /// Call `tick` every `interval` seconds, until it returns `false`.
+  ///
+  /// `tick` is given how late it's being called. If a frame is longer than
+  /// `interval`, it's called several times that frame to catch up, so it
+  /// always gets called the right number of times overall.
+  ///
+  /// Returns how late the last call was, which is also left over for the
+  /// next timed wait.
+  ///
+  /// Panics if `interval` isn't positive.
+  #[track_caller]
+  pub fn every<'b, F>(
+    &'b self,
+    interval: f32,
+    mut tick: F,
+  ) -> impl Future<Output = f32> + 'b
+  where
+    F: FnMut(f32) -> bool + 'b,
+  {
+    assert!(
+      interval > 0.0,
+      "Tried to do something every {} seconds",
+      interval
+    );
+    let point = YieldPoint::here(Some("every"));
+    async move {
+      let mut elapsed = self.carry.take();
+      loop {
+        while elapsed >= interval {
+          elapsed -= interval;
+          if !tick(elapsed) {
+            self.carry.set(elapsed);
+            return elapsed;
+          }
+        }
+        elapsed += self.ield_at(point, Y::default()).await;
+      }
+    }
+  }
+}
@@ -0,0 +1,187 @@
+//! Variable-rate mapping over iterators.
+//!
+//! [`GeneratorIterator`](crate::iter::GeneratorIterator) is strictly
+//! one query in, one yield out. A transducer can instead read any number of
+//! inputs before putting anything out, and put out any number of outputs for
+//! each input. That's what you want for chunkers, decoders, run-length
+//! encoders and the like.
+//!
+//! ```rust
+//! # use dedenne::*;
+//! // Run-length encode some characters
+//! let encoded = "aaabccdddd"
+//!   .chars()
+//!   .transduce(|t| async move {
+//!     let Some(mut current) = t.input().await else {
+//!       return;
+//!     };
+//!     let mut count = 1;
+//!     loop {
+//!       match t.input().await {
+//!         Some(c) if c == current => count += 1,
+//!         next => {
+//!           t.emit((current, count)).await;
+//!           match next {
+//!             Some(c) => {
+//!               current = c;
+//!               count = 1;
+//!             }
+//!             // End of input; we already flushed the last run
+//!             None => return,
+//!           }
+//!         }
+//!       }
+//!     }
+//!   })
+//!   .collect::<Vec<_>>();
+//! assert_eq!(encoded, vec![('a', 3), ('b', 1), ('c', 2), ('d', 4)]);
+//! ```
+
+use std::{fmt, future::Future, iter::Fuse, pin::Pin};
+
+use crate::{
+  iter::BoxedFutureMaker, GeneratorResponse, StartedGenerator, YieldPoint,
+  YieldWrapper,
+};
+
+/// Extension trait adding [`transduce`](TransduceExt::transduce)
+/// to all iterators.
+pub trait TransduceExt: Iterator + Sized {
+  /// Map over this iterator with a generator body that can take in and put
+  /// out items at whatever rate it likes.
+  ///
+  /// The body gets a [`Transducer`]. Call `t.input().await` to get the next
+  /// item (or `None` at the end of input), and `t.emit(x).await` to put
+  /// something out. Iteration ends when the body returns.
+  ///
+  /// Nothing runs until the first call to `next`.
+  fn transduce<O, F, Fut>(self, f: F) -> Transduce<Self, O>
+  where
+    F: FnOnce(Transducer<Self::Item, O>) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+    Self::Item: 'static,
+    O: 'static,
+  {
+    let maker = Box::new(move |y| {
+      Box::pin(f(Transducer { y })) as Pin<Box<dyn Future<Output = ()>>>
+    });
+    Transduce {
+      iter: self.fuse(),
+      state: TransduceState::Unstarted(maker),
+    }
+  }
+}
+
+impl<I: Iterator> TransduceExt for I {}
+
+/// The type of `t` in a [`transduce`](TransduceExt::transduce) body.
+pub struct Transducer<I, O> {
+  y: YieldWrapper<Option<I>, Step<O>>,
+}
+
+impl<I, O> Transducer<I, O> {
+  /// Get the next input, or `None` if there's no more.
+  ///
+  /// Once this returns `None` it will keep doing so.
+  #[track_caller]
+  pub fn input(&self) -> impl Future<Output = Option<I>> {
+    self
+      .y
+      .ield_at(YieldPoint::here(Some("input")), Step::NeedInput)
+  }
+
+  /// Put something out.
+  #[track_caller]
+  pub fn emit(&self, output: O) -> impl Future<Output = ()> {
+    let fut = self
+      .y
+      .ield_at(YieldPoint::here(Some("emit")), Step::Emit(output));
+    async move {
+      fut.await;
+    }
+  }
+}
+
+/// The iterator returned by [`TransduceExt::transduce`].
+pub struct Transduce<I: Iterator, O> {
+  iter: Fuse<I>,
+  state: TransduceState<I::Item, O>,
+}
+
+impl<I, O> Iterator for Transduce<I, O>
+where
+  I: Iterator,
+  I::Item: 'static,
+  O: 'static,
+{
+  type Item = O;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let (generator, resp) =
+        match std::mem::replace(&mut self.state, TransduceState::Done) {
+          TransduceState::Unstarted(maker) => StartedGenerator::run(maker),
+          TransduceState::Emitted(mut generator) => {
+            // What we answer with here doesn't matter;
+            // `emit` ignores it
+            let resp = generator.query(None);
+            (generator, resp)
+          }
+          TransduceState::WantsInput(mut generator) => {
+            let input = self.iter.next();
+            let resp = generator.query(input);
+            (generator, resp)
+          }
+          TransduceState::Done => return None,
+        };
+      match resp {
+        GeneratorResponse::Yielding(Step::Emit(output)) => {
+          self.state = TransduceState::Emitted(generator);
+          return Some(output);
+        }
+        GeneratorResponse::Yielding(Step::NeedInput) => {
+          self.state = TransduceState::WantsInput(generator);
+          // and go around again
+        }
+        GeneratorResponse::Done(()) => {
+          self.state = TransduceState::Done;
+          return None;
+        }
+      }
+    }
+  }
+}
+
+impl<I, O> fmt::Debug for Transduce<I, O>
+where
+  I: Iterator + fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let state = match &self.state {
+      TransduceState::Unstarted(..) => "Unstarted",
+      TransduceState::Emitted(..) => "Emitted",
+      TransduceState::WantsInput(..) => "WantsInput",
+      TransduceState::Done => "Done",
+    };
+    f.debug_struct("Transduce")
+      .field("iter", &self.iter)
+      .field("state", &state)
+      .finish()
+  }
+}
+
+/// What the body of a transducer wants.
+enum Step<O> {
+  Emit(O),
+  NeedInput,
+}
+
+/// Tracks the state of a `Transduce`
+enum TransduceState<I, O> {
+  Unstarted(BoxedFutureMaker<Option<I>, Step<O>, ()>),
+  /// The body just emitted something and is waiting to be resumed
+  Emitted(StartedGenerator<Step<O>, (), Option<I>>),
+  /// The body is waiting for the next input
+  WantsInput(StartedGenerator<Step<O>, (), Option<I>>),
+  Done,
+}
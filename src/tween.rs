@@ -0,0 +1,410 @@
+//! Animating values in generators that are queried with the frame time.
+//!
+//! [`y.tween(from, to, duration, easing)`](YieldWrapper::tween) yields a
+//! value partway between `from` and `to` every frame, for `duration`
+//! seconds. Bigger animations can be put together out of
+//! [`tween`]s with [`sequence`], [`parallel`] and [`repeat`] and then
+//! [`play`](YieldWrapper::play)ed all at once.
+//!
+//! Like the waits in the [`time`](crate::time) module, these carry over
+//! `y`'s leftover time, so back-to-back animations
+//! don't drift. The frame an animation finishes on always yields its end
+//! value, even if that frame went a bit past the end; the time it went
+//! past by, plus the next frame's, is carried over to whatever's next.
+//!
+//! ```rust
+//! # use dedenne::{*, tween::ease};
+//! let (mut gen, first) = StartedGenerator::run(|y| async move {
+//!   y.tween(0.0, 10.0, 1.0, ease::linear).await;
+//!   y.tween(10.0, 0.0, 0.5, ease::quad_in).await;
+//! });
+//! assert_eq!(first, GeneratorResponse::Yielding(0.0));
+//! let frames = std::iter::repeat(0.25)
+//!   .map(|dt| gen.query(dt))
+//!   .map_while(|resp| match resp {
+//!     GeneratorResponse::Yielding(x) => Some(x),
+//!     GeneratorResponse::Done(()) => None,
+//!   })
+//!   .collect::<Vec<_>>();
+//! assert_eq!(frames, [2.5, 5.0, 7.5, 10.0, 7.5, 0.0]);
+//! ```
+
+use std::future::Future;
+
+use crate::{YieldPoint, YieldWrapper};
+
+/// Values that can be smoothly moved between.
+pub trait Lerp {
+  /// Get the value `t` of the way from `self` to `to`.
+  ///
+  /// `t` is usually between 0 and 1, but some easing curves go past
+  /// either end, so it should do something sensible out there too.
+  fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+  fn lerp(&self, to: &Self, t: f32) -> Self {
+    self + (to - self) * t
+  }
+}
+
+impl Lerp for f64 {
+  fn lerp(&self, to: &Self, t: f32) -> Self {
+    self + (to - self) * t as f64
+  }
+}
+
+impl<T: Lerp, const N: usize> Lerp for [T; N] {
+  fn lerp(&self, to: &Self, t: f32) -> Self {
+    std::array::from_fn(|i| self[i].lerp(&to[i], t))
+  }
+}
+
+impl<A: Lerp, B: Lerp> Lerp for (A, B) {
+  fn lerp(&self, to: &Self, t: f32) -> Self {
+    (self.0.lerp(&to.0, t), self.1.lerp(&to.1, t))
+  }
+}
+
+/// Something with a value at every moment from 0 to some duration.
+pub trait Animation {
+  type Value;
+
+  /// How many seconds this takes.
+  fn duration(&self) -> f32;
+
+  /// The value `time` seconds in.
+  ///
+  /// `time` is always between 0 and `duration`.
+  fn sample(&self, time: f32) -> Self::Value;
+}
+
+/// See [`tween`].
+#[derive(Debug, Clone)]
+pub struct Tween<T, E> {
+  from: T,
+  to: T,
+  duration: f32,
+  easing: E,
+}
+
+/// Move from `from` to `to` over `duration` seconds.
+///
+/// `easing` maps how far through the tween it is (from 0 to 1)
+/// to how far between `from` and `to` the value should be.
+/// There are some standard ones in [`ease`].
+pub fn tween<T, E>(from: T, to: T, duration: f32, easing: E) -> Tween<T, E>
+where
+  T: Lerp,
+  E: Fn(f32) -> f32,
+{
+  Tween {
+    from,
+    to,
+    duration,
+    easing,
+  }
+}
+
+impl<T, E> Animation for Tween<T, E>
+where
+  T: Lerp,
+  E: Fn(f32) -> f32,
+{
+  type Value = T;
+
+  fn duration(&self) -> f32 {
+    self.duration
+  }
+
+  fn sample(&self, time: f32) -> T {
+    let t = if self.duration > 0.0 {
+      (time / self.duration).clamp(0.0, 1.0)
+    } else {
+      1.0
+    };
+    self.from.lerp(&self.to, (self.easing)(t))
+  }
+}
+
+/// See [`sequence`].
+#[derive(Debug, Clone)]
+pub struct Sequence<A, B> {
+  first: A,
+  second: B,
+}
+
+/// Play `first`, then `second`.
+pub fn sequence<A, B>(first: A, second: B) -> Sequence<A, B>
+where
+  A: Animation,
+  B: Animation<Value = A::Value>,
+{
+  Sequence { first, second }
+}
+
+impl<A, B> Animation for Sequence<A, B>
+where
+  A: Animation,
+  B: Animation<Value = A::Value>,
+{
+  type Value = A::Value;
+
+  fn duration(&self) -> f32 {
+    self.first.duration() + self.second.duration()
+  }
+
+  fn sample(&self, time: f32) -> A::Value {
+    let split = self.first.duration();
+    if time < split {
+      self.first.sample(time)
+    } else {
+      self.second.sample(time - split)
+    }
+  }
+}
+
+/// See [`parallel`].
+#[derive(Debug, Clone)]
+pub struct Parallel<A, B> {
+  a: A,
+  b: B,
+}
+
+/// Play `a` and `b` at the same time, getting both their values.
+///
+/// It lasts as long as the longer of the two; the shorter one stays at its
+/// end value until then.
+pub fn parallel<A, B>(a: A, b: B) -> Parallel<A, B>
+where
+  A: Animation,
+  B: Animation,
+{
+  Parallel { a, b }
+}
+
+impl<A, B> Animation for Parallel<A, B>
+where
+  A: Animation,
+  B: Animation,
+{
+  type Value = (A::Value, B::Value);
+
+  fn duration(&self) -> f32 {
+    self.a.duration().max(self.b.duration())
+  }
+
+  fn sample(&self, time: f32) -> Self::Value {
+    (
+      self.a.sample(time.min(self.a.duration())),
+      self.b.sample(time.min(self.b.duration())),
+    )
+  }
+}
+
+/// See [`repeat`].
+#[derive(Debug, Clone)]
+pub struct Repeat<A> {
+  inner: A,
+  times: u32,
+}
+
+/// Play `inner` `times` times in a row.
+pub fn repeat<A: Animation>(inner: A, times: u32) -> Repeat<A> {
+  Repeat { inner, times }
+}
+
+impl<A: Animation> Animation for Repeat<A> {
+  type Value = A::Value;
+
+  fn duration(&self) -> f32 {
+    self.inner.duration() * self.times as f32
+  }
+
+  fn sample(&self, time: f32) -> A::Value {
+    let once = self.inner.duration();
+    if time >= self.duration() || once <= 0.0 {
+      self.inner.sample(once)
+    } else {
+      self.inner.sample(time.rem_euclid(once))
+    }
+  }
+}
+
+impl<T> YieldWrapper<f32, T> {
+  /// Yield values from `from` to `to` over `duration` seconds,
+  /// finishing with `to` itself.
+  ///
+  /// Returns how far past `duration` it was when it finished,
+  /// just like [`wait_secs`](Self::wait_secs).
+  #[track_caller]
+  pub fn tween<'b, E>(
+    &'b self,
+    from: T,
+    to: T,
+    duration: f32,
+    easing: E,
+  ) -> impl Future<Output = f32> + 'b
+  where
+    T: Lerp,
+    E: Fn(f32) -> f32 + 'b,
+  {
+    let point = YieldPoint::here(Some("tween"));
+    self.play_at(point, tween(from, to, duration, easing))
+  }
+
+  /// Yield the value of `animation` every frame until it's over,
+  /// including its value at the very end.
+  ///
+  /// Returns how far past the end it was when it finished.
+  #[track_caller]
+  pub fn play<'b, A>(&'b self, animation: A) -> impl Future<Output = f32> + 'b
+  where
+    A: Animation<Value = T> + 'b,
+  {
+    self.play_at(YieldPoint::here(Some("play")), animation)
+  }
+
+  async fn play_at<A>(&self, point: YieldPoint, animation: A) -> f32
+  where
+    A: Animation<Value = T>,
+  {
+    let duration = animation.duration();
+    let mut elapsed = self.carry.take();
+    while elapsed < duration {
+      elapsed += self.ield_at(point, animation.sample(elapsed)).await;
+    }
+    // Make sure the end gets seen; the frame that shows it still counts
+    elapsed += self.ield_at(point, animation.sample(duration)).await;
+    let overshoot = elapsed - duration;
+    self.carry.set(overshoot);
+    overshoot
+  }
+}
+
+/// Standard easing curves, for passing to [`tween`].
+///
+/// They all go from 0 at 0 to 1 at 1. The `back` and `elastic` ones
+/// go past those in between.
+/// See <https://easings.net> for what they look like.
+pub mod ease {
+  use std::f32::consts::PI;
+
+  pub fn linear(t: f32) -> f32 {
+    t
+  }
+
+  pub fn quad_in(t: f32) -> f32 {
+    t * t
+  }
+
+  pub fn quad_out(t: f32) -> f32 {
+    1.0 - quad_in(1.0 - t)
+  }
+
+  pub fn quad_in_out(t: f32) -> f32 {
+    in_out(t, quad_in)
+  }
+
+  pub fn cubic_in(t: f32) -> f32 {
+    t * t * t
+  }
+
+  pub fn cubic_out(t: f32) -> f32 {
+    1.0 - cubic_in(1.0 - t)
+  }
+
+  pub fn cubic_in_out(t: f32) -> f32 {
+    in_out(t, cubic_in)
+  }
+
+  pub fn sine_in(t: f32) -> f32 {
+    1.0 - (t * PI / 2.0).cos()
+  }
+
+  pub fn sine_out(t: f32) -> f32 {
+    (t * PI / 2.0).sin()
+  }
+
+  pub fn sine_in_out(t: f32) -> f32 {
+    (1.0 - (t * PI).cos()) / 2.0
+  }
+
+  pub fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 {
+      0.0
+    } else {
+      2f32.powf(10.0 * t - 10.0)
+    }
+  }
+
+  pub fn expo_out(t: f32) -> f32 {
+    1.0 - expo_in(1.0 - t)
+  }
+
+  pub fn expo_in_out(t: f32) -> f32 {
+    in_out(t, expo_in)
+  }
+
+  /// Pulls back a bit before going.
+  pub fn back_in(t: f32) -> f32 {
+    const C1: f32 = 1.70158;
+    const C3: f32 = C1 + 1.0;
+    C3 * t * t * t - C1 * t * t
+  }
+
+  /// Goes a bit past the end and comes back.
+  pub fn back_out(t: f32) -> f32 {
+    1.0 - back_in(1.0 - t)
+  }
+
+  pub fn back_in_out(t: f32) -> f32 {
+    in_out(t, back_in)
+  }
+
+  /// Wobbles around the end before settling.
+  pub fn elastic_out(t: f32) -> f32 {
+    if t <= 0.0 {
+      0.0
+    } else if t >= 1.0 {
+      1.0
+    } else {
+      2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+    }
+  }
+
+  pub fn elastic_in(t: f32) -> f32 {
+    1.0 - elastic_out(1.0 - t)
+  }
+
+  /// Bounces off the end like a dropped ball.
+  pub fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+      N * t * t
+    } else if t < 2.0 / D {
+      let t = t - 1.5 / D;
+      N * t * t + 0.75
+    } else if t < 2.5 / D {
+      let t = t - 2.25 / D;
+      N * t * t + 0.9375
+    } else {
+      let t = t - 2.625 / D;
+      N * t * t + 0.984375
+    }
+  }
+
+  pub fn bounce_in(t: f32) -> f32 {
+    1.0 - bounce_out(1.0 - t)
+  }
+
+  /// Do the first half of `curve` sped up, then the same backwards.
+  fn in_out(t: f32, curve: fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+      curve(t * 2.0) / 2.0
+    } else {
+      1.0 - curve((1.0 - t) * 2.0) / 2.0
+    }
+  }
+}
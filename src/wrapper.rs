@@ -4,9 +4,12 @@
 //! The main docs for how this crate actually works are on
 //! the structs in the root.
 
-use std::{future::Future, marker::PhantomData};
+use std::{fmt, future::Future, iter::Repeat, marker::PhantomData, pin::Pin};
 
-use crate::{GeneratorResponse, StartedGenerator, YieldWrapper};
+use crate::{
+  iter::{FeedbackIterator, GeneratorIterator},
+  Budget, GeneratorResponse, StartedGenerator, YieldPoint, YieldWrapper,
+};
 
 /// Silly convenience wrapper over a started or unstarted generator.
 ///
@@ -16,14 +19,33 @@ use crate::{GeneratorResponse, StartedGenerator, YieldWrapper};
 /// I can stop using generics whenever I want
 pub struct Generator<F, Fut, S, Y, R, Q = ()> {
   inner: GeneratorWrapperInner<F, Fut, S, Y, R, Q>,
+  /// Filled in by the `_ref` methods.
+  last_response: Option<GeneratorResponse<Y, R>>,
 }
 
+/// A [`Generator`] whose closure and future are boxed,
+/// so you can actually write out its type.
+///
+/// This is what the [combinators](crate::combinators) make out of a
+/// `Generator`.
+pub type BoxedGenerator<S, Y, R, Q = ()> = Generator<
+  Box<dyn FnOnce(YieldWrapper<Q, Y>, S) -> Pin<Box<dyn Future<Output = R>>>>,
+  Pin<Box<dyn Future<Output = R>>>,
+  S,
+  Y,
+  R,
+  Q,
+>;
+
 /// Inner `Either`-like enum for the generator wrapper.
 pub enum GeneratorWrapperInner<F, Fut, S, Y, R, Q> {
   Unstarted {
     future_maker: F,
     _phantom: PhantomData<(Fut, S)>,
   },
+  /// Only around while the generator is being started.
+  /// If it's still here afterwards, the closure panicked while starting,
+  /// and the generator is poisoned.
   Starting,
   Started(StartedGenerator<Y, R, Q>),
 }
@@ -41,7 +63,10 @@ where
       future_maker: f,
       _phantom: PhantomData,
     };
-    Self { inner }
+    Self {
+      inner,
+      last_response: None,
+    }
   }
 
   pub fn start(&mut self, init: S) -> GeneratorResponse<Y, R> {
@@ -54,6 +79,8 @@ where
           GeneratorWrapperInner::Unstarted { future_maker, .. } => future_maker,
           _ => unreachable!(),
         };
+        // If this panics, we stay in `Starting`, which is how we know
+        // we're poisoned
         let (started, out) = StartedGenerator::run_with(init, future_maker);
         self.inner = GeneratorWrapperInner::Started(started);
         out
@@ -61,21 +88,143 @@ where
       GeneratorWrapperInner::Started(..) => {
         panic!("don't start a generator you've already started!")
       }
-      GeneratorWrapperInner::Starting => {
-        unreachable!()
-      }
+      GeneratorWrapperInner::Starting => panic_poisoned(),
     }
   }
 
   pub fn query(&mut self, query: Q) -> GeneratorResponse<Y, R> {
+    self.started_mut().query(query)
+  }
+
+  /// Like `start`, but hangs on to the response,
+  /// and gives you a reference to it.
+  ///
+  /// See [`last_response`](Self::last_response).
+  pub fn start_ref(&mut self, init: S) -> &GeneratorResponse<Y, R> {
+    let resp = self.start(init);
+    self.last_response.insert(resp)
+  }
+
+  /// Like `query`, but hangs on to the response,
+  /// and gives you a reference to it.
+  ///
+  /// See [`last_response`](Self::last_response).
+  pub fn query_ref(&mut self, query: Q) -> &GeneratorResponse<Y, R> {
+    let resp = self.query(query);
+    self.last_response.insert(resp)
+  }
+
+  /// The response from the last call to `start_ref` or `query_ref`,
+  /// if there was one and it hasn't been taken.
+  pub fn last_response(&self) -> Option<&GeneratorResponse<Y, R>> {
+    self.last_response.as_ref()
+  }
+
+  /// Take the response from the last call to `start_ref` or `query_ref`.
+  pub fn take_last_response(&mut self) -> Option<GeneratorResponse<Y, R>> {
+    self.last_response.take()
+  }
+
+  /// See [`StartedGenerator::query_within`].
+  pub fn query_within(
+    &mut self,
+    budget: Budget,
+    query: Q,
+  ) -> GeneratorResponse<Y, R> {
+    self.started_mut().query_within(budget, query)
+  }
+
+  /// See [`StartedGenerator::suspended_at`].
+  /// This is also `None` if the generator hasn't started.
+  pub fn suspended_at(&self) -> Option<YieldPoint> {
+    match &self.inner {
+      GeneratorWrapperInner::Started(started) => started.suspended_at(),
+      _ => None,
+    }
+  }
+
+  /// Turn a started generator into an iterator over `iter`.
+  ///
+  /// See [`StartedGenerator::iter_over`]. To start it from the iterator
+  /// instead, use [`jumpstart_iter_over`](Self::jumpstart_iter_over).
+  pub fn iter_over<I>(self, iter: I) -> GeneratorIterator<Y, R, Q, I> {
+    self.into_started().iter_over(iter)
+  }
+
+  /// Turn an unstarted generator into an iterator over `iter`,
+  /// which will start it with `init` on the first call to `next`.
+  pub fn jumpstart_iter_over<I>(
+    self,
+    init: S,
+    iter: I,
+  ) -> GeneratorIterator<Y, R, Q, I>
+  where
+    I: Iterator<Item = Q>,
+    F: 'static,
+    S: 'static,
+  {
+    let future_maker = self.into_future_maker();
+    StartedGenerator::jumpstart_iter_over(iter, move |y| future_maker(y, init))
+  }
+
+  /// See [`StartedGenerator::iter_with`].
+  pub fn iter_with<FB>(
+    self,
+    query: Q,
+    feedback: FB,
+  ) -> FeedbackIterator<Y, R, Q, FB>
+  where
+    FB: FnMut(&Y) -> Option<Q>,
+  {
+    self.into_started().iter_with(query, feedback)
+  }
+
+  /// See [`StartedGenerator::jumpstart_iter_with`].
+  pub fn jumpstart_iter_with<FB>(
+    self,
+    init: S,
+    feedback: FB,
+  ) -> FeedbackIterator<Y, R, Q, FB>
+  where
+    FB: FnMut(&Y) -> Option<Q>,
+    F: 'static,
+    S: 'static,
+  {
+    let future_maker = self.into_future_maker();
+    StartedGenerator::jumpstart_iter_with(feedback, move |y| {
+      future_maker(y, init)
+    })
+  }
+
+  /// Take the closure back out of an unstarted generator.
+  pub(crate) fn into_future_maker(self) -> F {
     match self.inner {
-      GeneratorWrapperInner::Started(ref mut started) => started.query(query),
+      GeneratorWrapperInner::Unstarted { future_maker, .. } => future_maker,
+      GeneratorWrapperInner::Starting => panic_poisoned(),
+      GeneratorWrapperInner::Started(_) => {
+        panic!("expected a Generator that hasn't started yet")
+      }
+    }
+  }
+
+  /// Take the started generator out of this.
+  pub fn into_started(self) -> StartedGenerator<Y, R, Q> {
+    match self.inner {
+      GeneratorWrapperInner::Started(started) => started,
       GeneratorWrapperInner::Unstarted { .. } => {
-        panic!("don't query a Generator you haven't started!")
+        panic!("don't use a Generator you haven't started!")
       }
-      GeneratorWrapperInner::Starting => {
-        unreachable!()
+      GeneratorWrapperInner::Starting => panic_poisoned(),
+    }
+  }
+
+  fn started_mut(&mut self) -> &mut StartedGenerator<Y, R, Q> {
+    match self.inner {
+      GeneratorWrapperInner::Started(ref mut started) => started,
+      GeneratorWrapperInner::Unstarted { .. } => {
+        panic!("don't query a Generator you haven't started!")
       }
+      GeneratorWrapperInner::Starting => panic_poisoned(),
     }
   }
 
@@ -86,6 +235,24 @@ where
       GeneratorWrapperInner::Started(_) => true,
     }
   }
+
+  /// Whether the generator has returned its `R`.
+  pub fn is_finished(&self) -> bool {
+    match &self.inner {
+      GeneratorWrapperInner::Started(started) => started.is_finished(),
+      _ => false,
+    }
+  }
+
+  /// Whether the generator panicked while starting or being queried.
+  /// Using it again will panic.
+  pub fn is_poisoned(&self) -> bool {
+    match &self.inner {
+      GeneratorWrapperInner::Unstarted { .. } => false,
+      GeneratorWrapperInner::Starting => true,
+      GeneratorWrapperInner::Started(started) => started.is_poisoned(),
+    }
+  }
 }
 
 impl<F, Fut, S, Y, R> Generator<F, Fut, S, Y, R, ()>
@@ -97,4 +264,75 @@ where
   pub fn resume(&mut self) -> GeneratorResponse<Y, R> {
     self.query(())
   }
+
+  /// Convenience wrapper for `query_ref(())`.
+  pub fn resume_ref(&mut self) -> &GeneratorResponse<Y, R> {
+    self.query_ref(())
+  }
+
+  /// Convenience wrapper for `query_within(budget, ())`.
+  pub fn resume_within(&mut self, budget: Budget) -> GeneratorResponse<Y, R> {
+    self.query_within(budget, ())
+  }
+
+  /// Turn a started generator into an iterator that repeatedly feeds `()`
+  /// into it.
+  pub fn iter(self) -> GeneratorIterator<Y, R, (), Repeat<()>> {
+    self.into_started().iter()
+  }
+
+  /// Turn an unstarted generator into an iterator that repeatedly feeds `()`
+  /// into it, starting it with `init` on the first call to `next`.
+  pub fn jumpstart_iter(
+    self,
+    init: S,
+  ) -> GeneratorIterator<Y, R, (), Repeat<()>>
+  where
+    F: 'static,
+    S: 'static,
+  {
+    self.jumpstart_iter_over(init, std::iter::repeat(()))
+  }
+}
+
+/// Iterates over the generator, starting it first if it hasn't been.
+///
+/// An unstarted generator is started like [`Generator::jumpstart_iter`], so
+/// its first yield comes out of the iterator too. A started one is like
+/// [`Generator::iter`].
+impl<F, Fut, Y, R> IntoIterator for Generator<F, Fut, (), Y, R, ()>
+where
+  F: FnOnce(YieldWrapper<(), Y>, ()) -> Fut + 'static,
+  Fut: Future<Output = R> + 'static,
+  Y: 'static,
+  R: 'static,
+{
+  type Item = Y;
+  type IntoIter = GeneratorIterator<Y, R, (), Repeat<()>>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    if self.has_started() {
+      self.iter()
+    } else {
+      self.jumpstart_iter(())
+    }
+  }
+}
+
+impl<F, Fut, S, Y, R, Q> fmt::Debug for Generator<F, Fut, S, Y, R, Q> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut out = f.debug_struct("Generator");
+    match &self.inner {
+      GeneratorWrapperInner::Unstarted { .. } => {
+        out.field("state", &"Unstarted")
+      }
+      GeneratorWrapperInner::Starting => out.field("state", &"Poisoned"),
+      GeneratorWrapperInner::Started(started) => out.field("started", started),
+    };
+    out.finish_non_exhaustive()
+  }
+}
+
+fn panic_poisoned() -> ! {
+  panic!("This Generator panicked while starting, and can't be used anymore")
 }
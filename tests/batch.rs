@@ -0,0 +1,80 @@
+use dedenne::{batch::BatchGenerator, GeneratorResponse};
+
+#[test]
+fn emit_and_flush() {
+  let (mut gen, resp) = BatchGenerator::run(|y| async move {
+    y.emit('a');
+    y.emit('b');
+    assert_eq!(y.pending_len(), 2);
+    let q = y.flush().await;
+    assert_eq!(y.pending_len(), 0);
+    // Flushing an empty buffer still suspends
+    let q2 = y.flush().await;
+    q + q2
+  });
+  assert_eq!(resp, GeneratorResponse::Yielding(vec!['a', 'b']));
+  assert_eq!(
+    gen.suspended_at().and_then(|point| point.label),
+    Some("flush")
+  );
+  assert_eq!(gen.query(1), GeneratorResponse::Yielding(vec![]));
+  assert_eq!(gen.query(2), GeneratorResponse::Done(3));
+}
+
+#[test]
+fn query_batch_keeps_leftover_queries() {
+  let (mut gen, _) = BatchGenerator::run(|y| async move {
+    let mut total = 0;
+    for _ in 0..3 {
+      let q: u32 = y.flush().await;
+      for i in 0..q {
+        y.emit(i);
+      }
+      total += q;
+    }
+    total
+  });
+  let mut queries = [1, 2, 3, 4].into_iter();
+  let (items, done) = gen.query_batch(&mut queries);
+  // The last three were emitted but never flushed
+  assert_eq!(items, vec![0, 0, 1, 0, 1, 2]);
+  assert_eq!(done, Some(6));
+  assert_eq!(queries.next(), Some(4));
+}
+
+#[test]
+fn query_batch_not_done() {
+  let (mut gen, _) = BatchGenerator::run(|y| async move {
+    loop {
+      y.emit(1);
+      y.emit(2);
+      y.flush().await;
+    }
+  });
+  let (items, done): (_, Option<()>) = gen.query_batch([(), ()]);
+  assert_eq!(items, vec![1, 2, 1, 2]);
+  assert_eq!(done, None);
+}
+
+#[test]
+fn leftovers_yielded_on_return() {
+  let (mut gen, resp) = BatchGenerator::run(|y| async move {
+    y.emit(1);
+    y.flush().await;
+    y.emit(2);
+    y.emit(3);
+    "done"
+  });
+  assert_eq!(resp, GeneratorResponse::Yielding(vec![1]));
+  assert_eq!(gen.resume(), GeneratorResponse::Yielding(vec![2, 3]));
+  assert!(!gen.is_finished());
+  assert_eq!(gen.resume(), GeneratorResponse::Done("done"));
+  assert!(gen.is_finished());
+
+  // Even if it never flushes at all
+  let (mut gen, resp) = BatchGenerator::run(|y| async move {
+    y.emit('a');
+  });
+  assert_eq!(resp, GeneratorResponse::Yielding(vec!['a']));
+  assert_eq!(gen.query_batch([(), ()]), (vec![], Some(())));
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use dedenne::*;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Progress {
+  Working,
+  Found(u32),
+}
+
+#[test]
+fn iteration_budget() {
+  let (mut gen, resp) = StartedGenerator::run(|y| async move {
+    let mut total = 0;
+    for x in 0..100u32 {
+      total += x;
+      y.checkpoint(Progress::Working).await;
+    }
+    y.ield(Progress::Found(total)).await;
+    "All done!"
+  });
+  // No budget while starting, so it suspends on the very first checkpoint
+  assert_eq!(resp, GeneratorResponse::Yielding(Progress::Working));
+
+  let mut frames = 0;
+  let found = loop {
+    frames += 1;
+    match gen.resume_within(Budget::iterations(10)) {
+      GeneratorResponse::Yielding(Progress::Working) => {}
+      GeneratorResponse::Yielding(Progress::Found(total)) => break total,
+      GeneratorResponse::Done(_) => panic!("should have found something"),
+    }
+  };
+  assert_eq!(found, (0..100).sum());
+  assert_eq!(frames, 10);
+
+  assert_eq!(
+    gen.resume_within(Budget::unlimited()),
+    GeneratorResponse::Done("All done!")
+  );
+}
+
+#[test]
+fn checkpoint_passes_query() {
+  let (mut gen, resp) = StartedGenerator::run(|y| async move {
+    let mut seen = Vec::new();
+    for _ in 0..6 {
+      if let Some(q) = y.checkpoint("working").await {
+        seen.push(q);
+      }
+    }
+    seen
+  });
+  assert_eq!(resp, GeneratorResponse::Yielding("working"));
+
+  assert_eq!(
+    gen.query_within(Budget::iterations(2), 1),
+    GeneratorResponse::Yielding("working")
+  );
+  assert_eq!(
+    gen.query_within(Budget::iterations(2), 2),
+    GeneratorResponse::Yielding("working")
+  );
+  assert_eq!(
+    gen.query_within(Budget::unlimited(), 3),
+    GeneratorResponse::Done(vec![1, 2, 3])
+  );
+}
+
+#[test]
+fn time_budget() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    loop {
+      std::thread::sleep(Duration::from_millis(1));
+      y.checkpoint(()).await;
+    }
+  });
+
+  // A time budget must eventually run out even with no iteration limit
+  let resp = gen.resume_within(Budget::time(Duration::from_millis(5)));
+  assert_eq!(resp, GeneratorResponse::<(), ()>::Yielding(()));
+}
@@ -0,0 +1,243 @@
+use std::{cell::RefCell, rc::Rc};
+
+use dedenne::*;
+
+async fn counter(y: YieldWrapper<u32, u32>) -> &'static str {
+  let mut total = 0;
+  for x in 0..5 {
+    total += y.ield(x).await;
+  }
+  assert_eq!(total, 10);
+  "All done!"
+}
+
+#[test]
+fn map_yield_and_return() {
+  let (mut gen, resp) = StartedGenerator::run(counter)
+    .map_yield(|x| x * 10)
+    .map_return(|r| r.len());
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+  for expected in [10, 20, 30, 40] {
+    assert_eq!(gen.query(2), GeneratorResponse::Yielding(expected));
+  }
+  assert_eq!(gen.query(2), GeneratorResponse::Done(9));
+}
+
+#[test]
+fn map_query() {
+  let (gen, resp) = StartedGenerator::run(counter)
+    .map_query(|s: &str| s.parse::<u32>().unwrap());
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+
+  let mut iter = gen.iter_over(["1", "2", "3", "4", "0"].into_iter());
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+  assert_eq!(iter.consume_response(), Some("All done!"));
+}
+
+#[test]
+fn filter_yield() {
+  let (mut gen, resp) =
+    StartedGenerator::run(counter).filter_yield(|x| x % 2 == 1, 100);
+  // 0 gets skipped, so it's answered with 100 right away
+  assert_eq!(resp, GeneratorResponse::Yielding(1));
+  assert_eq!(gen.query(1), GeneratorResponse::Yielding(3));
+  // This total is wrong, so the inner generator would panic
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    gen.query(1);
+  }));
+  assert!(result.is_err());
+}
+
+#[test]
+fn filter_yield_passes_through_matches() {
+  // Yields 0 to 9, and returns every query it got
+  let (gen, resp) = StartedGenerator::run(|y| async move {
+    let mut got = Vec::new();
+    for i in 0..10u32 {
+      got.push(y.ield(i).await);
+    }
+    got
+  })
+  .filter_yield(|x| x % 3 == 0, 'd');
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+
+  let mut iter = gen.iter_over("abcdef".chars());
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![3, 6, 9]);
+  // The rejected yields got the default without ever reaching us
+  assert_eq!(
+    iter.consume_response(),
+    Some("addbddcddd".chars().collect())
+  );
+}
+
+#[test]
+fn take_yields() {
+  let (gen, resp) = StartedGenerator::run(counter).take_yields(3);
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+  let mut iter = gen.iter_over(std::iter::repeat(1));
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![1, 2]);
+  assert_eq!(iter.consume_response(), Some(None));
+
+  let (gen, _) = StartedGenerator::run(counter).take_yields(10);
+  let mut iter = gen.iter_over([1, 2, 3, 4, 0].into_iter());
+  assert_eq!((&mut iter).count(), 4);
+  assert_eq!(iter.consume_response(), Some(Some("All done!")));
+}
+
+#[test]
+fn unstarted_wrapper() {
+  let mut gen = Generator::new(|y, start: u32| async move {
+    let got = y.ield(start).await;
+    y.ield(got + 1).await;
+    got
+  })
+  .map_yield(|x: u32| x.to_string())
+  .map_query(|q: bool| q as u32);
+
+  assert_eq!(gen.start(7), GeneratorResponse::Yielding("7".to_string()));
+  assert_eq!(
+    gen.query(true),
+    GeneratorResponse::Yielding("2".to_string())
+  );
+  assert_eq!(gen.query(false), GeneratorResponse::Done(1));
+}
+
+fn count_to(n: u32) -> combinators::Running<u32, u32, ()> {
+  StartedGenerator::run(move |y| async move {
+    for x in 0..n {
+      y.ield(x).await;
+    }
+    n
+  })
+}
+
+#[test]
+fn chain() {
+  let b = Generator::new(|y, start: u32| async move {
+    y.ield(start * 100).await;
+    "All done!"
+  });
+  let (gen, resp) = combinators::chain(count_to(2), b);
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+
+  let mut iter = gen.iter();
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![1, 200]);
+  assert_eq!(iter.consume_response(), Some("All done!"));
+}
+
+#[test]
+fn zip() {
+  let (mut gen, resp) = combinators::zip(count_to(3), count_to(2));
+  assert_eq!(resp, GeneratorResponse::Yielding((0, 0)));
+  assert_eq!(gen.resume(), GeneratorResponse::Yielding((1, 1)));
+  assert_eq!(
+    gen.resume(),
+    GeneratorResponse::Done((
+      GeneratorResponse::Yielding(2),
+      GeneratorResponse::Done(2)
+    ))
+  );
+}
+
+#[test]
+fn interleave() {
+  let (gen, resp) = combinators::interleave(count_to(3), count_to(1));
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+  let mut iter = gen.iter();
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![0, 1, 2]);
+  assert_eq!(iter.consume_response(), Some((3, 1)));
+}
+
+#[test]
+fn select() {
+  let evens = StartedGenerator::run(|y| async move {
+    let mut total = 0;
+    while total < 10 {
+      total += y.ield(format!("evens: {}", total)).await;
+    }
+    "evens won"
+  });
+  let odds_seen = Rc::new(RefCell::new(Vec::new()));
+  let seen = odds_seen.clone();
+  let odds = StartedGenerator::run(|y| async move {
+    let mut total = 0;
+    while total < 10 {
+      let query = y.ield(format!("odds: {}", total)).await;
+      seen.borrow_mut().push(query);
+      total += query;
+    }
+    total
+  });
+
+  let (mut gen, resp) = combinators::select(evens, odds, |q| q % 2 == 0);
+  assert_eq!(resp, GeneratorResponse::Yielding("evens: 0".to_string()));
+  assert_eq!(
+    gen.query(1),
+    GeneratorResponse::Yielding("odds: 1".to_string())
+  );
+  assert_eq!(
+    gen.query(3),
+    GeneratorResponse::Yielding("odds: 4".to_string())
+  );
+  assert_eq!(
+    gen.query(4),
+    GeneratorResponse::Yielding("evens: 4".to_string())
+  );
+  assert_eq!(
+    gen.query(7),
+    GeneratorResponse::Done(combinators::Either::Second(11))
+  );
+  // Including the very first one
+  assert_eq!(*odds_seen.borrow(), [1, 3, 7]);
+}
+
+/// Yields the running total of the numbers it's fed,
+/// and returns the total at end of input.
+fn summer() -> combinators::Running<u32, u32, Option<u32>> {
+  StartedGenerator::run(|y| async move {
+    let mut total = 0;
+    while let Some(x) = y.ield(total).await {
+      total += x;
+    }
+    total
+  })
+}
+
+#[test]
+fn pipe_upstream_finishes() {
+  let (gen, resp) = count_to(4).then(summer());
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+
+  let mut iter = gen.iter();
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![0, 1, 3, 6]);
+  assert_eq!(iter.consume_response(), Some((Some(4), 6)));
+}
+
+#[test]
+fn pipe_downstream_finishes() {
+  let (gen, resp) = combinators::pipe(count_to(100), summer().take_yields(3));
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+
+  let mut iter = gen.iter();
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![0, 1]);
+  assert_eq!(iter.consume_response(), Some((None, None)));
+}
+
+#[test]
+fn pipe_three_stages() {
+  let doubler = StartedGenerator::run(|y| async move {
+    let mut input = y.ield(None).await;
+    while let Some(x) = input {
+      input = y.ield(Some(x * 2)).await;
+    }
+  });
+  let keep_some =
+    |stage: combinators::Running<Option<u32>, (), Option<u32>>| {
+      stage.map_yield(|x| x.unwrap_or(0))
+    };
+
+  let (gen, _) = count_to(3).then(keep_some(doubler)).then(summer());
+  let mut iter = gen.iter();
+  assert_eq!((&mut iter).last(), Some(6));
+  assert_eq!(iter.consume_response(), Some((Some((Some(3), ())), 6)));
+}
@@ -0,0 +1,101 @@
+use std::sync::LazyLock;
+
+use dedenne::{coverage::Coverage, GeneratorResponse, StartedGenerator};
+
+async fn greet(y: dedenne::YieldWrapper<bool, &'static str>) -> String {
+  let polite = y.ield_labeled("ask politeness", "Polite?").await;
+  if polite {
+    y.ield("Good day to you.").await;
+    "polite".to_string()
+  } else {
+    y.ield("Sup.").await;
+    "rude".to_string()
+  }
+}
+
+fn transcript(
+  (mut gen, _): (
+    StartedGenerator<&'static str, String, bool>,
+    GeneratorResponse<&'static str, String>,
+  ),
+  polite: bool,
+) -> String {
+  gen.query(polite);
+  match gen.query(true) {
+    GeneratorResponse::Done(r) => r,
+    GeneratorResponse::Yielding(_) => panic!("too many yields"),
+  }
+}
+
+#[test]
+fn counts_hits() {
+  let coverage = Coverage::new();
+  transcript(coverage.run(greet), true);
+  transcript(coverage.run(greet), true);
+  // Not watched
+  transcript(StartedGenerator::run(greet), false);
+
+  let report = coverage.report();
+  let counts = report.sites.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+  // The rude branch never got hit
+  assert_eq!(counts, vec![2, 2]);
+  assert_eq!(report.sites[0].0.label, Some("ask politeness"));
+
+  let text = report.to_string();
+  assert_eq!(text.lines().count(), 2);
+  assert!(text.contains("\"ask politeness\" at tests/coverage.rs"));
+}
+
+#[test]
+fn only_counts_its_own_generator() {
+  let coverage = Coverage::new();
+  let (mut outer, _) = coverage.run(|y| async move {
+    // Driving another generator from inside this one doesn't count it
+    let (mut inner, _) = StartedGenerator::<u8, (), ()>::run(|y| async move {
+      y.ield_labeled("inner", 1).await;
+    });
+    inner.resume();
+    y.ield_labeled("outer", ()).await;
+  });
+  outer.resume();
+
+  let labels = coverage
+    .report()
+    .sites
+    .iter()
+    .map(|(point, count)| (point.label, *count))
+    .collect::<Vec<_>>();
+  assert_eq!(labels, vec![(Some("outer"), 1)]);
+}
+
+#[test]
+fn watch_a_running_generator() {
+  let coverage = Coverage::new();
+  let started = StartedGenerator::run(greet);
+  // The first yield already happened
+  coverage.watch(&started.0);
+  coverage.watch(&started.0);
+  transcript(started, false);
+  let counts = coverage
+    .report()
+    .sites
+    .iter()
+    .map(|(point, count)| (point.label, *count))
+    .collect::<Vec<_>>();
+  assert_eq!(counts, vec![(None, 1)]);
+}
+
+static SUITE: LazyLock<Coverage> = LazyLock::new(Coverage::new);
+
+#[test]
+fn shared_between_tests_a() {
+  transcript(SUITE.run(greet), true);
+}
+
+#[test]
+fn shared_between_tests_b() {
+  transcript(SUITE.run(greet), false);
+  let (gen, _) = SUITE.run(greet);
+  let first = gen.suspended_at().unwrap();
+  assert!(SUITE.hits_at(first) >= 2);
+}
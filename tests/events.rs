@@ -0,0 +1,48 @@
+use std::{cell::RefCell, rc::Rc};
+
+use dedenne::*;
+
+#[test]
+fn wait_for_map_pulls_out_a_value() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    y.wait_for_map(|key: &Result<char, u32>| key.ok()).await
+  });
+  assert_eq!(gen.query(Err(1)), GeneratorResponse::Yielding(()));
+  assert_eq!(gen.query(Err(2)), GeneratorResponse::Yielding(()));
+  assert_eq!(gen.query(Ok('x')), GeneratorResponse::Done('x'));
+}
+
+#[test]
+fn timeouts_count_queries() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let first = y.wait_for_within(2, |n: &u32| *n > 10).await;
+    y.ield(Some(first)).await;
+    let second = y.wait_for_map_within(2, |n| n.checked_sub(10)).await;
+    y.ield(Some(second)).await;
+    y.wait_for_within(0, |_| true).await
+  });
+  assert_eq!(gen.query(1), GeneratorResponse::Yielding(None));
+  assert_eq!(gen.query(2), GeneratorResponse::Yielding(Some(None)));
+  assert_eq!(gen.query(0), GeneratorResponse::Yielding(None));
+  assert_eq!(gen.query(15), GeneratorResponse::Yielding(Some(Some(5))));
+  // A limit of 0 gives up straight away
+  assert_eq!(gen.query(0), GeneratorResponse::Done(None));
+}
+
+#[test]
+fn discarded_queries_go_to_the_hook() {
+  let seen = Rc::new(RefCell::new(Vec::new()));
+  let seen2 = seen.clone();
+  let (mut gen, _) = StartedGenerator::<(), (), u32>::run(|y| async move {
+    y.wait_for(|n| *n == 0).await;
+    let logged = y.on_discard(move |n| seen2.borrow_mut().push(n));
+    logged.wait_for_any(&[5, 6]).await;
+    logged.wait_for_within(1, |n| *n == 7).await;
+    y.wait_for(|n| *n == 0).await;
+  });
+  for n in [1, 0, 2, 3, 6, 8, 4, 0] {
+    gen.query(n);
+  }
+  assert!(gen.is_finished());
+  assert_eq!(*seen.borrow(), [2, 3, 8]);
+}
@@ -0,0 +1,90 @@
+use std::ops::ControlFlow;
+
+use dedenne::{fallible::*, GeneratorResponse, StartedGenerator};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Oops(u32);
+
+fn check(x: u32) -> Result<u32, Oops> {
+  if x < 5 {
+    Ok(x)
+  } else {
+    Err(Oops(x))
+  }
+}
+
+#[test]
+fn question_mark() {
+  let (mut gen, resp) =
+    TryGenerator::<u32, u32, Oops, u32>::run(|y| async move {
+      let mut total = 0;
+      loop {
+        total += check(y.ield(total).await)?;
+        if total > 6 {
+          return Ok(total);
+        }
+      }
+    });
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+  assert_eq!(gen.query(4), GeneratorResponse::Yielding(4));
+  assert_eq!(gen.query(9).transpose(), Err(Oops(9)));
+}
+
+/// Yields the squares of its queries, failing on big ones.
+fn squares() -> TryGenerator<Result<u32, Oops>, &'static str, Oops, u32> {
+  let (gen, _) = StartedGenerator::run(|y| async move {
+    let mut x = y.ield(Ok(0)).await;
+    for _ in 0..3 {
+      x = y.ield(check(x).map(|x| x * x)).await;
+    }
+    check(x)?;
+    Ok("All done!")
+  });
+  gen
+}
+
+#[test]
+fn iter_ok() {
+  let mut iter = squares().try_iter_over([1, 2, 3, 4].into_iter());
+  assert_eq!(
+    (&mut iter).collect::<Result<Vec<_>, _>>(),
+    Ok(vec![1, 4, 9])
+  );
+  assert_eq!(iter.error(), None);
+  assert_eq!(iter.consume_response(), Some(Ok("All done!")));
+}
+
+#[test]
+fn iter_yielded_error() {
+  let mut iter = squares().try_iter_over([1, 7, 3, 4].into_iter());
+  assert_eq!(iter.next(), Some(Ok(1)));
+  assert_eq!(iter.next(), Some(Err(Oops(7))));
+  assert_eq!(iter.next(), None);
+  assert_eq!(iter.error(), Some(&Oops(7)));
+  assert_eq!(iter.consume_response(), Some(Err(Oops(7))));
+}
+
+#[test]
+fn iter_returned_error() {
+  let mut iter = squares().try_iter_over([1, 2, 3, 8].into_iter());
+  assert_eq!(
+    (&mut iter).collect::<Vec<_>>(),
+    vec![Ok(1), Ok(4), Ok(9), Err(Oops(8))]
+  );
+  assert_eq!(iter.next(), None);
+  assert_eq!(iter.error(), Some(&Oops(8)));
+  assert_eq!(iter.consume_response(), Some(Err(Oops(8))));
+}
+
+#[test]
+fn conversions() {
+  let resp: GeneratorResponse<u32, &str> = GeneratorResponse::Yielding(1);
+  assert_eq!(ControlFlow::from(resp), ControlFlow::Continue(1));
+  let resp: GeneratorResponse<u32, &str> = ControlFlow::Break("done").into();
+  assert_eq!(resp, GeneratorResponse::Done("done"));
+  assert_eq!(Result::from(resp), Err("done"));
+  assert_eq!(
+    GeneratorResponse::from(Ok::<u32, &str>(2)),
+    GeneratorResponse::Yielding(2)
+  );
+}
@@ -21,3 +21,114 @@ fn smoke_test() {
 
   assert_eq!(iter.consume_response(), Some("All done!"));
 }
+
+#[test]
+fn resume_after_exhausted() {
+  let mut iter =
+    StartedGenerator::jumpstart_iter_over(0u32..2, |y| async move {
+      let mut total = 0;
+      for _ in 0..4 {
+        total += y.ield(total).await;
+      }
+      total
+    });
+  assert_eq!(iter.size_hint(), (0, Some(3)));
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![0, 0, 1]);
+  // Fused, even though the generator isn't done
+  assert_eq!(iter.next(), None);
+  assert_eq!(iter.response(), None);
+
+  let mut iter = iter.try_consume_response().unwrap_err();
+  assert_eq!(iter.resume_with(10), Some(11));
+  assert_eq!(iter.resume_with(100), None);
+  assert_eq!(iter.response(), Some(&111));
+  assert_eq!(iter.resume_with(1000), None);
+
+  match iter.into_parts() {
+    iter::GeneratorIteratorParts::GeneratorDone(total, mut rest) => {
+      assert_eq!(total, 111);
+      assert_eq!(rest.next(), None);
+    }
+    _ => panic!("should have been done"),
+  }
+}
+
+#[test]
+fn into_parts_keeps_generator() {
+  let (generator, _) = StartedGenerator::run(|y| async move {
+    let a = y.ield("a").await;
+    let b = y.ield("b").await;
+    a + b
+  });
+  let mut iter = generator.iter_over(std::iter::once(1));
+  assert_eq!(iter.next(), Some("b"));
+  assert_eq!(iter.next(), None);
+  assert!(format!("{:?}", iter).contains("ExhaustedIterator"));
+
+  let iter::GeneratorIteratorParts::ExhaustedIterator(mut generator, _) =
+    iter.into_parts()
+  else {
+    panic!("should have run out of queries")
+  };
+  assert_eq!(generator.query(2), GeneratorResponse::Done(3));
+}
+
+#[test]
+fn feedback() {
+  // Agent walks toward 10, observing its position each step
+  let mut walk = StartedGenerator::jumpstart_iter_with(
+    |&pos: &i32| (pos != 10).then_some(if pos < 10 { 3 } else { -1 }),
+    |y| async move {
+      let mut pos = 0;
+      loop {
+        pos += y.ield(pos).await;
+      }
+    },
+  );
+  assert_eq!(
+    (&mut walk).collect::<Vec<_>>(),
+    vec![0, 3, 6, 9, 12, 11, 10]
+  );
+  assert_eq!(walk.next(), None);
+  let walk = walk.try_consume_response().unwrap_err();
+  let mut generator = walk.into_generator().unwrap();
+  assert_eq!(generator.query(5), GeneratorResponse::Yielding(15));
+}
+
+#[test]
+fn feedback_until_done() {
+  let (generator, resp) = StartedGenerator::run(|y| async move {
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+      seen.push(y.ield(seen.len()).await);
+    }
+    seen
+  });
+  assert_eq!(resp, GeneratorResponse::Yielding(0));
+
+  let mut iter =
+    generator.iter_with('a', |&len| Some((b'a' + len as u8) as char));
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![1, 2]);
+  assert_eq!(iter.consume_response(), Some(vec!['a', 'b', 'c']));
+}
+
+#[test]
+fn panicking_while_starting_poisons() {
+  use std::panic::{catch_unwind, AssertUnwindSafe};
+
+  let mut iter = StartedGenerator::<u32, (), u32>::jumpstart_iter_over(
+    0u32..,
+    |_| async move { panic!("oh no") },
+  );
+  assert!(catch_unwind(AssertUnwindSafe(|| iter.next())).is_err());
+  assert_eq!(
+    format!("{:?}", iter),
+    "GeneratorIterator { state: \"Poisoned\", .. }"
+  );
+  let err = catch_unwind(AssertUnwindSafe(|| iter.resume_with(1))).unwrap_err();
+  assert!(err
+    .downcast_ref::<&str>()
+    .is_some_and(|msg| msg.contains("panicked partway through")));
+  assert!(catch_unwind(AssertUnwindSafe(|| iter.next())).is_err());
+  assert!(catch_unwind(AssertUnwindSafe(|| iter.into_parts())).is_err());
+}
@@ -0,0 +1,75 @@
+use dedenne::{lending::LendingGenerator, GeneratorResponse};
+
+/// A "frame buffer" that gets redrawn in place every step.
+fn frames() -> LendingGenerator<Vec<u8>, usize, u8> {
+  LendingGenerator::run(|y| async move {
+    let mut buf = vec![0; 4];
+    let mut frames = 0;
+    loop {
+      let (fill, back) = y.lend(buf).await;
+      buf = back;
+      if fill == 0 {
+        return frames;
+      }
+      buf.iter_mut().for_each(|px| *px = fill);
+      frames += 1;
+    }
+  })
+}
+
+#[test]
+fn lends_and_gets_back() {
+  let mut gen = frames();
+  assert_eq!(gen.current(), Some(&vec![0; 4]));
+  let ptr = gen.current().unwrap().as_ptr();
+
+  assert_eq!(gen.query(7), GeneratorResponse::Yielding(&vec![7; 4]));
+  // Same allocation every time
+  assert_eq!(gen.current().unwrap().as_ptr(), ptr);
+
+  // Changes made from outside get handed back, but this body paints over them
+  gen.current_mut().unwrap()[0] = 1;
+  let frame = match gen.query(3) {
+    GeneratorResponse::Yielding(frame) => frame.clone(),
+    GeneratorResponse::Done(_) => unreachable!(),
+  };
+  assert_eq!(frame, vec![3; 4]);
+
+  assert_eq!(gen.query(0), GeneratorResponse::Done(2));
+  assert!(gen.is_finished());
+  assert_eq!(gen.current(), None);
+}
+
+#[test]
+fn body_sees_current_mut_changes() {
+  let mut gen = LendingGenerator::run(|y| async move {
+    let mut log = vec!["body"];
+    for _ in 0..2 {
+      let ((), back) = y.lend(log).await;
+      log = back;
+      log.push("body");
+    }
+    log
+  });
+  gen.current_mut().unwrap().push("caller");
+  assert_eq!(
+    gen.resume(),
+    GeneratorResponse::Yielding(&vec!["body", "caller", "body"])
+  );
+  gen.current_mut().unwrap().clear();
+  assert_eq!(gen.resume(), GeneratorResponse::Done(vec!["body"]));
+}
+
+#[test]
+fn try_run_immediate_return() {
+  let res = LendingGenerator::<u8, _>::try_run(|_y| async move { "nothing" });
+  assert_eq!(res.unwrap_err(), "nothing");
+}
+
+#[test]
+#[should_panic = "after it had finished"]
+fn query_after_finished() {
+  let mut gen = frames();
+  gen.query(0);
+  gen.query(1);
+}
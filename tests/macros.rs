@@ -0,0 +1,47 @@
+use dedenne::*;
+
+#[test]
+fn simple() {
+  let mut generator = gen!(start => {
+    for x in 0..start {
+      yield_!(x);
+    }
+    for x in (0..start).rev() {
+      yield_!(x);
+    }
+    "All done!"
+  });
+
+  assert_eq!(generator.start(2), GeneratorResponse::Yielding(0));
+  assert_eq!(generator.resume(), GeneratorResponse::Yielding(1));
+  assert_eq!(generator.resume(), GeneratorResponse::Yielding(1));
+  assert_eq!(generator.resume(), GeneratorResponse::Yielding(0));
+  assert_eq!(generator.resume(), GeneratorResponse::Done("All done!"));
+}
+
+#[test]
+fn typed() {
+  let mut generator = gen!((a, b): (u32, u32), yield &'static str, query u32, return u32 => {
+    let mut total = a + b;
+    total += yield_!("first");
+    total += yield_!("door" => "second");
+    total
+  });
+
+  assert_eq!(
+    generator.start((1, 2)),
+    GeneratorResponse::Yielding("first")
+  );
+  assert_eq!(generator.query(10), GeneratorResponse::Yielding("second"));
+  assert_eq!(generator.query(100), GeneratorResponse::Done(113));
+}
+
+#[test]
+fn unit_yield() {
+  let mut generator = gen!(_: (), return &str => {
+    yield_!();
+    "done"
+  });
+  assert_eq!(generator.start(()), GeneratorResponse::Yielding(()));
+  assert_eq!(generator.resume(), GeneratorResponse::Done("done"));
+}
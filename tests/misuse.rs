@@ -0,0 +1,92 @@
+//! Using a `YieldWrapper` wrong should give a clear `panic!`,
+//! never an `unreachable!`.
+
+use std::{cell::RefCell, rc::Rc};
+
+use dedenne::*;
+
+type Stash = Rc<RefCell<Option<Rc<YieldWrapper<(), u32>>>>>;
+
+/// Start a generator that leaks its `YieldWrapper` and then suspends.
+fn leaky() -> (StartedGenerator<u32, (), ()>, Stash) {
+  let stash: Stash = Default::default();
+  let stash2 = stash.clone();
+  let (generator, _) = StartedGenerator::run(|y| async move {
+    let y = Rc::new(y);
+    *stash2.borrow_mut() = Some(y.clone());
+    y.ield(1).await;
+  });
+  (generator, stash)
+}
+
+#[test]
+#[should_panic = "after its generator had finished"]
+fn yield_after_finished() {
+  let (mut generator, stash) = leaky();
+  assert_eq!(generator.resume(), GeneratorResponse::Done(()));
+  let y = stash.borrow_mut().take().unwrap();
+  drop(y.ield(2));
+}
+
+#[test]
+#[should_panic = "outside of any generator"]
+fn yield_outside_generator() {
+  let (_generator, stash) = leaky();
+  let y = stash.borrow_mut().take().unwrap();
+  drop(y.ield(2));
+}
+
+#[test]
+#[should_panic = "from inside a different generator"]
+fn yield_from_other_generator() {
+  let (_generator, stash) = leaky();
+  let y = stash.borrow_mut().take().unwrap();
+  let _ = StartedGenerator::<u32, (), ()>::run(|_other_y| async move {
+    y.ield(2).await;
+  });
+}
+
+#[test]
+#[should_panic = "polled again after it had already completed"]
+fn await_twice() {
+  let (mut generator, _) =
+    StartedGenerator::<u32, (), ()>::run(|y| async move {
+      let mut fut = Box::pin(y.ield(1));
+      (&mut fut).await;
+      (&mut fut).await;
+    });
+  generator.resume();
+}
+
+#[test]
+#[should_panic = "returned without awaiting the yield"]
+fn return_without_awaiting() {
+  let _ = StartedGenerator::<u32, (), ()>::run(|y| async move {
+    let _forgotten = y.ield(1);
+  });
+}
+
+#[test]
+#[should_panic = "suspended without yielding"]
+fn await_foreign_future() {
+  let _ = StartedGenerator::<u32, (), ()>::run(|_y| async move {
+    std::future::pending::<()>().await;
+  });
+}
+
+#[test]
+fn nested_generators_are_fine() {
+  let (mut outer, resp) = StartedGenerator::run(|y| async move {
+    let (mut inner, mut resp) = StartedGenerator::run(|y| async move {
+      y.ield(1).await;
+      y.ield(2).await;
+    });
+    while let GeneratorResponse::Yielding(x) = resp {
+      y.ield(x * 10).await;
+      resp = inner.resume();
+    }
+  });
+  assert_eq!(resp, GeneratorResponse::Yielding(10));
+  assert_eq!(outer.resume(), GeneratorResponse::Yielding(20));
+  assert_eq!(outer.resume(), GeneratorResponse::Done(()));
+}
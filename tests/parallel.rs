@@ -0,0 +1,104 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use dedenne::{parallel::ParallelSet, GeneratorResponse, StartedGenerator};
+
+/// Does some busywork and yields how far along it is.
+async fn worker(y: dedenne::YieldWrapper<u64, u64>, id: u64) -> u64 {
+  let mut acc = id;
+  for step in 0..5 {
+    // Make the threads finish in a different order every time
+    if (id + step).is_multiple_of(3) {
+      thread::sleep(Duration::from_millis(1));
+    }
+    acc = acc.wrapping_mul(31).wrapping_add(step);
+    let seed = y.ield(acc).await;
+    acc ^= seed;
+  }
+  acc
+}
+
+#[test]
+fn matches_running_them_in_a_loop() {
+  let mut expected = Vec::new();
+  let mut generators = (0..10u64)
+    .map(|id| {
+      let (generator, resp) = StartedGenerator::run(move |y| worker(y, id));
+      expected.push(vec![resp]);
+      generator
+    })
+    .collect::<Vec<_>>();
+  for tick in 0..5 {
+    for (generator, log) in generators.iter_mut().zip(&mut expected) {
+      log.push(generator.query(tick));
+    }
+  }
+
+  for threads in [1, 3, 8] {
+    let mut set = ParallelSet::with_threads(threads);
+    let mut handles = HashMap::new();
+    let mut got = (0..10u64)
+      .map(|id| {
+        let (handle, first) = set.spawn(move |y| worker(y, id));
+        handles.insert(handle, id as usize);
+        vec![GeneratorResponse::Yielding(first.unwrap())]
+      })
+      .collect::<Vec<_>>();
+    for tick in 0..5 {
+      let yields = set.tick_all(tick);
+      assert!(yields.windows(2).all(|pair| pair[0].0 < pair[1].0));
+      for (handle, yielded) in yields {
+        got[handles[&handle]].push(GeneratorResponse::Yielding(yielded));
+      }
+      for (handle, done) in set.take_finished() {
+        got[handles[&handle]].push(GeneratorResponse::Done(done));
+      }
+    }
+    assert_eq!(got, expected, "with {} threads", threads);
+    assert!(set.is_empty());
+  }
+}
+
+#[test]
+fn cancel_and_immediate_return() {
+  let mut set = ParallelSet::<(), &str, &str>::with_threads(2);
+  let (quick, first) = set.spawn(|_| async move { "quick" });
+  assert_eq!(first, None);
+  let (a, _) = set.spawn(|y| async move {
+    loop {
+      y.ield("a").await;
+    }
+  });
+  let (b, _) = set.spawn(|y| async move {
+    loop {
+      y.ield("b").await;
+    }
+  });
+  assert_eq!(set.take_finished(), vec![(quick, "quick")]);
+  assert_eq!(set.tick_all(()), vec![(a, "a"), (b, "b")]);
+  assert!(set.cancel(a));
+  assert!(!set.cancel(a));
+  assert_eq!(set.tick_all(()), vec![(b, "b")]);
+  assert_eq!(set.handles().collect::<Vec<_>>(), vec![b]);
+}
+
+#[test]
+fn panics_come_back_to_the_caller() {
+  let mut set = ParallelSet::<(), &str, ()>::with_threads(2);
+  let (bad, _) = set.spawn(|y| async move {
+    y.ield("bad").await;
+    panic!("from a worker")
+  });
+  let (fine, _) = set.spawn(|y| async move {
+    loop {
+      y.ield("fine").await;
+    }
+  });
+  // The healthy one's yield isn't lost
+  assert_eq!(set.tick_all(()), vec![(fine, "fine")]);
+  let panicked = set.take_panicked();
+  assert_eq!(panicked.len(), 1);
+  assert_eq!(panicked[0].0, bad);
+  assert_eq!(panicked[0].1.downcast_ref::<&str>(), Some(&"from a worker"));
+  assert_eq!(set.handles().collect::<Vec<_>>(), vec![fine]);
+  assert_eq!(set.tick_all(()), vec![(fine, "fine")]);
+}
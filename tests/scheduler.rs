@@ -0,0 +1,379 @@
+use std::{
+  cell::{Cell, RefCell},
+  rc::Rc,
+};
+
+use dedenne::{scheduler::*, StartedGenerator};
+
+/// Yields its name `n` times, then returns the total time it was given.
+fn script(
+  set: &mut CoroutineSet<u32, &'static str, u32>,
+  name: &'static str,
+  n: usize,
+) -> Handle {
+  let (handle, first) = set.spawn(move |y| async move {
+    let mut total = 0;
+    for _ in 0..n {
+      total += y.ield(name).await;
+    }
+    total
+  });
+  assert_eq!(first, if n > 0 { Some(name) } else { None });
+  handle
+}
+
+#[test]
+fn ticks_in_spawn_order() {
+  let mut set = CoroutineSet::new();
+  let c = script(&mut set, "c", 3);
+  let a = script(&mut set, "a", 1);
+  let b = script(&mut set, "b", 2);
+  assert!(c < a && a < b);
+  assert_eq!(set.handles().collect::<Vec<_>>(), vec![c, a, b]);
+
+  assert_eq!(set.tick_all(1), vec![(c, "c"), (b, "b")]);
+  assert_eq!(set.take_finished(), vec![(a, 1)]);
+  assert_eq!(set.tick_all(2), vec![(c, "c")]);
+  assert_eq!(set.tick_all(3), vec![]);
+  assert_eq!(set.take_finished(), vec![(b, 3), (c, 6)]);
+  assert!(set.is_empty());
+}
+
+#[test]
+fn cancel_and_tick_one() {
+  let mut set = CoroutineSet::new();
+  let a = script(&mut set, "a", 5);
+  let b = script(&mut set, "b", 1);
+
+  assert_eq!(set.tick(a, 10), Some("a"));
+  assert!(set.cancel(a));
+  assert!(!set.cancel(a));
+  assert!(!set.contains(a));
+  assert_eq!(set.tick(a, 10), None);
+
+  assert_eq!(set.tick(b, 7), None);
+  assert_eq!(set.take_finished(), vec![(b, 7)]);
+  assert_eq!(set.len(), 0);
+}
+
+#[test]
+fn immediate_return_and_insert() {
+  let mut set = CoroutineSet::new();
+  let empty = script(&mut set, "empty", 0);
+  assert!(!set.contains(empty));
+
+  let (other, first) = set.insert(StartedGenerator::run(|y| async move {
+    y.ield("other").await;
+    100
+  }));
+  assert_eq!(first, Some("other"));
+  assert_eq!(set.tick_all(0), vec![]);
+  assert_eq!(set.take_finished(), vec![(empty, 0), (other, 100)]);
+}
+
+#[test]
+fn mail_is_delivered_in_order() {
+  let mut set = CoroutineSet::<(), String, Vec<u32>, u32>::new();
+  // Collects three messages
+  let (inbox, first) = set.spawn(|y| async move {
+    let mut got = Vec::new();
+    while got.len() < 3 {
+      got.push(y.recv().await);
+      y.ield(format!("got {:?}", got)).await;
+    }
+    got
+  });
+  assert_eq!(first, None);
+  assert!(set.is_receiving(inbox));
+
+  // Spawned after the inbox, so the inbox sees its mail next tick
+  let (sender, _) = set.spawn(move |y| async move {
+    y.ield("ready".to_string()).await;
+    assert!(y.send(inbox, 1).await);
+    assert!(y.send(inbox, 2).await);
+    y.ield("sent".to_string()).await;
+    vec![]
+  });
+
+  assert_eq!(set.tick_all(()), vec![(sender, "sent".to_string())]);
+  // It's got mail now, so it's awake, even though it hasn't run yet
+  assert!(!set.is_receiving(inbox));
+  assert!(!set.is_asleep(inbox));
+
+  assert!(set.send(inbox, 3));
+  let yields = set.tick_all(());
+  assert_eq!(yields, vec![(inbox, "got [1]".to_string())]);
+  assert_eq!(set.take_finished(), vec![(sender, vec![])]);
+
+  // The rest are already waiting, so `recv` doesn't suspend for them
+  assert_eq!(set.tick_all(()), vec![(inbox, "got [1, 2]".to_string())]);
+  assert_eq!(set.tick(inbox, ()), Some("got [1, 2, 3]".to_string()));
+  assert_eq!(set.tick(inbox, ()), None);
+  assert_eq!(set.take_finished(), vec![(inbox, vec![1, 2, 3])]);
+  assert!(!set.send(inbox, 4));
+}
+
+#[test]
+fn mail_to_later_coroutine_arrives_same_tick() {
+  let mut set = CoroutineSet::<(), &str, (), &str>::new();
+  // The receiver doesn't exist yet when the sender is spawned
+  let receiver_handle = Rc::new(Cell::new(None));
+  let (sender, _) = set.spawn({
+    let receiver_handle = receiver_handle.clone();
+    |y| async move {
+      y.ield("about to send").await;
+      let receiver = receiver_handle.get().unwrap();
+      y.send(receiver, "hello").await;
+    }
+  });
+  let (receiver, _) = set.spawn(|y| async move {
+    let msg = y.recv().await;
+    y.ield(msg).await;
+  });
+  receiver_handle.set(Some(receiver));
+
+  assert_eq!(set.tick_all(()), vec![(receiver, "hello")]);
+  assert_eq!(set.take_finished(), vec![(sender, ())]);
+}
+
+#[test]
+fn cancel_drops_mail() {
+  let mut set = CoroutineSet::<(), (), (), u8>::new();
+  let (h, _) = set.spawn(|y| async move {
+    y.recv().await;
+  });
+  assert!(set.send(h, 1));
+  assert!(set.cancel(h));
+  assert!(!set.send(h, 2));
+  assert_eq!(set.tick_all(()), vec![]);
+  assert_eq!(set.take_finished(), vec![]);
+}
+
+#[test]
+fn sleepers_are_skipped() {
+  let mut set = CoroutineSet::<(), u32, ()>::new();
+  let signal = set.new_signal();
+  for i in 0..1000 {
+    set.spawn(move |y| async move {
+      y.wait_signal(signal).await;
+      y.ield(i).await;
+    });
+  }
+  assert_eq!(set.len(), 1000);
+  assert_eq!(set.awake_len(), 0);
+  assert_eq!(set.tick_all(()), vec![]);
+
+  set.raise(signal);
+  let yields = set.tick_all(());
+  assert_eq!(yields.len(), 1000);
+  assert!(yields.iter().map(|(_, i)| *i).eq(0..1000));
+  assert_eq!(set.awake_len(), 1000);
+  assert_eq!(set.tick_all(()), vec![]);
+  assert!(set.is_empty());
+}
+
+#[test]
+fn wait_until_frame() {
+  let mut set = CoroutineSet::<(), u64, ()>::new();
+  let (h, first) = set.spawn(|y| async move {
+    // Already past this, so no waiting
+    y.wait_until(0).await;
+    y.ield(y.frame()).await;
+    y.wait_until(5).await;
+    y.ield(y.frame()).await;
+  });
+  assert_eq!(first, Some(0));
+  assert_eq!(set.tick_all(()), vec![]);
+  assert!(set.is_asleep(h));
+  assert!(!set.is_receiving(h));
+  for _ in 2..5 {
+    assert_eq!(set.tick_all(()), vec![]);
+  }
+  assert_eq!(set.frame(), 4);
+  assert_eq!(set.tick_all(()), vec![(h, 5)]);
+}
+
+#[test]
+fn wait_for_other_coroutine() {
+  let mut set = CoroutineSet::<(), &str, ()>::new();
+  let (short, _) = set.spawn(|y| async move {
+    y.ield("short").await;
+  });
+  let (forever, _) = set.spawn(|y| async move {
+    loop {
+      y.ield("forever").await;
+    }
+  });
+  let (watcher, _) = set.spawn(move |y| async move {
+    y.wait_for_child(short).await;
+    y.ield("short is done").await;
+    y.wait_for_child(forever).await;
+    y.ield("forever is done").await;
+    // Not running any more, so this doesn't wait
+    y.wait_for_child(short).await;
+  });
+
+  assert_eq!(
+    set.tick_all(()),
+    vec![(forever, "forever"), (watcher, "short is done")]
+  );
+  assert_eq!(set.tick_all(()), vec![(forever, "forever")]);
+  assert!(set.cancel(forever));
+  assert_eq!(set.tick_all(()), vec![(watcher, "forever is done")]);
+  assert_eq!(set.tick_all(()), vec![]);
+  assert_eq!(set.take_finished(), vec![(short, ()), (watcher, ())]);
+}
+
+#[test]
+fn join_gets_returns_and_panics() {
+  let mut set = CoroutineSet::<(), &str, u32>::new();
+  let (parent, _) = set.spawn(|y| async move {
+    let good = y.spawn(|y| async move {
+      y.ield("good").await;
+      1
+    });
+    let bad = y.spawn(|_| async move { panic!("oh no") });
+    let gone = y.spawn(|y| async move {
+      loop {
+        y.ield("gone").await;
+      }
+    });
+    assert!(y.cancel(gone));
+    let mut total = 0;
+    if let Exit::Returned(n) = y.join(good).await {
+      total += n;
+    }
+    match y.join(bad).await {
+      Exit::Panicked(payload) => {
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"oh no"))
+      }
+      _ => panic!("expected a panic"),
+    }
+    assert!(matches!(y.join(gone).await, Exit::Cancelled));
+    total + 10
+  });
+
+  // `gone` is cancelled before it ever gets to run
+  let yields = set.tick_all(());
+  assert_eq!(yields.iter().map(|(_, y)| *y).collect::<Vec<_>>(), ["good"]);
+  assert_eq!(set.parent(yields[0].0), Some(parent));
+  set.tick_all(());
+  set.tick_all(());
+  assert_eq!(set.take_finished(), vec![(parent, 11)]);
+  assert!(set.is_empty());
+}
+
+struct Noisy(u32, Rc<RefCell<Vec<u32>>>);
+
+impl Drop for Noisy {
+  fn drop(&mut self) {
+    self.1.borrow_mut().push(self.0);
+  }
+}
+
+#[test]
+fn cancelling_cascades_youngest_first() {
+  let dropped = Rc::new(RefCell::new(Vec::new()));
+  let mut set = CoroutineSet::<(), (), ()>::new();
+  let log = dropped.clone();
+  let (root, _) = set.spawn(move |y| async move {
+    let _noisy = Noisy(0, log.clone());
+    for i in 1..=2 {
+      let log = log.clone();
+      y.spawn(move |y| async move {
+        let _noisy = Noisy(i, log.clone());
+        y.spawn(move |y| async move {
+          let _noisy = Noisy(i * 10, log);
+          loop {
+            y.ield(()).await;
+          }
+        });
+        loop {
+          y.ield(()).await;
+        }
+      });
+    }
+    loop {
+      y.ield(()).await;
+    }
+  });
+
+  set.tick_all(());
+  assert_eq!(set.len(), 5);
+  assert!(set.cancel(root));
+  assert_eq!(*dropped.borrow(), vec![20, 2, 10, 1, 0]);
+  assert!(set.is_empty());
+}
+
+#[test]
+fn returning_cancels_children() {
+  let mut set = CoroutineSet::<(), &str, ()>::new();
+  let (parent, _) = set.spawn(|y| async move {
+    y.spawn(|y| async move {
+      loop {
+        y.ield("child").await;
+      }
+    });
+    y.ield("parent").await;
+    y.ield("parent").await;
+  });
+  let yields = set.tick_all(());
+  assert_eq!(yields.len(), 2);
+  let child = yields[1].0;
+  assert_eq!(set.parent(child), Some(parent));
+  assert_eq!(set.tick_all(()), vec![]);
+  assert!(!set.contains(child));
+  assert_eq!(set.take_finished(), vec![(parent, ())]);
+}
+
+#[test]
+fn top_level_panics_are_collected() {
+  let mut set = CoroutineSet::<(), &str, ()>::new();
+  let (bad, _) = set.spawn(|y| async move {
+    y.ield("bad").await;
+    panic!("top level")
+  });
+  let (fine, _) = set.spawn(|y| async move {
+    loop {
+      y.ield("fine").await;
+    }
+  });
+  // The rest of the tick still happens
+  assert_eq!(set.tick_all(()), vec![(fine, "fine")]);
+  assert!(!set.contains(bad));
+  let panicked = set.take_panicked();
+  assert_eq!(panicked.len(), 1);
+  assert_eq!(panicked[0].0, bad);
+  assert_eq!(panicked[0].1.downcast_ref::<&str>(), Some(&"top level"));
+  assert!(set.take_panicked().is_empty());
+  assert_eq!(set.take_finished(), vec![]);
+}
+
+#[test]
+fn late_joins_still_work() {
+  let mut set = CoroutineSet::<(), &str, u32>::new();
+  let (cutscene, _) = set.spawn(|y| async move {
+    let slow = y.spawn(|y| async move {
+      for _ in 0..3 {
+        y.ield("slow").await;
+      }
+      1
+    });
+    let fast = y.spawn(|_| async move { 10 });
+    let mut total = 0;
+    if let Exit::Returned(n) = y.join(slow).await {
+      total += n;
+    }
+    y.ield("slow is done").await;
+    // `fast` ended ages ago, but its exit is still waiting
+    if let Exit::Returned(n) = y.join(fast).await {
+      total += n;
+    }
+    total
+  });
+  for _ in 0..6 {
+    set.tick_all(());
+  }
+  assert!(set.take_panicked().is_empty());
+  assert_eq!(set.take_finished(), vec![(cutscene, 11)]);
+}
@@ -0,0 +1,88 @@
+use dedenne::{sink::*, GeneratorResponse, StartedGenerator};
+
+/// Adds up numbers until end of input, acknowledging each with the count.
+fn summer() -> Sink<usize, u32, u32> {
+  Sink::run(|y| async move {
+    let mut total = 0;
+    let mut count = 0;
+    while let Some(x) = y.ield(count).await {
+      total += x;
+      count += 1;
+    }
+    total
+  })
+}
+
+#[test]
+fn extend_then_finish() {
+  let mut sink = summer();
+  assert_eq!(sink.ack(), Some(&0));
+  sink.extend([1, 2, 3]);
+  sink.extend(vec![4]);
+  assert_eq!(sink.ack(), Some(&4));
+  assert!(!sink.is_done());
+  assert_eq!(sink.finish(), 10);
+}
+
+#[test]
+fn feed_all() {
+  // Reads exactly three items
+  let mut sink: Sink<(), u32, u32> = Sink::run(|y| async move {
+    let mut total = 0;
+    for _ in 0..3 {
+      total += y.ield(()).await.expect("ran out of input");
+    }
+    total
+  });
+
+  let mut start = [1, 2].into_iter();
+  assert_eq!(sink.feed_all(&mut start), Err(NeedMore));
+  assert_eq!(start.next(), None);
+  let mut rest = [3, 4, 5].into_iter();
+  assert_eq!(sink.feed_all(&mut rest), Ok(6));
+  assert_eq!(rest.next(), Some(4));
+
+  // The sink is spent now
+  assert!(sink.is_done());
+  assert!(!sink.feed(6));
+  assert_eq!(sink.response(), None);
+}
+
+#[test]
+#[should_panic = "already taken"]
+fn feed_all_twice() {
+  let mut sink: Sink<(), &str, u32> = Sink::run(|_y| async move { "nope" });
+  assert_eq!(sink.feed_all([]), Ok("nope"));
+  let _ = sink.feed_all([]);
+}
+
+#[test]
+fn fold() {
+  assert_eq!(summer().fold(1..=4), 10);
+  assert_eq!(summer().fold([]), 0);
+
+  // Stops taking items once it's returned
+  let first_two: Sink<(), Vec<char>, char> = Sink::run(|y| async move {
+    let mut out = Vec::new();
+    while let Some(c) = y.ield(()).await {
+      out.push(c);
+      if out.len() == 2 {
+        break;
+      }
+    }
+    out
+  });
+  let mut chars = "abcd".chars();
+  assert_eq!(first_two.fold(&mut chars), ['a', 'b']);
+  assert_eq!(chars.as_str(), "cd");
+}
+
+#[test]
+fn already_done() {
+  let (generator, resp) = StartedGenerator::run(|_y| async move { "nope" });
+  assert_eq!(resp, GeneratorResponse::Done("nope"));
+  let mut sink: Sink<(), _, u32> = Sink::new((generator, resp));
+  assert_eq!(sink.response(), Some(&"nope"));
+  sink.extend([1, 2, 3]);
+  assert_eq!(sink.finish(), "nope");
+}
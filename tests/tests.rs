@@ -88,3 +88,72 @@ fn panic_in_generator() {
   generator.resume();
   panic!("should never get here!");
 }
+
+#[test]
+fn suspended_at() {
+  let (mut generator, _) = StartedGenerator::run(|y| async move {
+    y.ield(1).await;
+    y.ield_labeled("waiting for door", 2).await;
+  });
+
+  let first = generator.suspended_at().unwrap();
+  assert_eq!(first.label, None);
+  assert_eq!(first.location.file(), file!());
+
+  generator.resume();
+  let second = generator.suspended_at().unwrap();
+  assert_eq!(second.label, Some("waiting for door"));
+  assert_eq!(second.location.line(), first.location.line() + 1);
+
+  assert!(matches!(generator.resume(), GeneratorResponse::Done(())));
+  assert_eq!(generator.suspended_at(), None);
+}
+
+#[test]
+#[should_panic(expected = "the yield at tests/tests.rs")]
+fn missing_await_reports_location() {
+  let _ = StartedGenerator::<i32, ()>::run(|y| async move {
+    #[allow(clippy::let_underscore_future)]
+    let _ = y.ield(1);
+    y.ield(2).await;
+  });
+}
+
+#[test]
+fn return_without_yielding() {
+  let (_, resp) =
+    StartedGenerator::<(), _>::run(|_y| async move { "Didn't need to!" });
+  assert_eq!(resp, GeneratorResponse::Done("Didn't need to!"));
+
+  // The usual reason: bailing out before the first yield
+  let countdown = |from: u32| {
+    StartedGenerator::<u32, _>::run(move |y| async move {
+      if from == 0 {
+        return "nothing to count";
+      }
+      for n in (1..=from).rev() {
+        y.ield(n).await;
+      }
+      "liftoff"
+    })
+  };
+  let (mut gen, resp) = countdown(2);
+  assert_eq!(resp, GeneratorResponse::Yielding(2));
+  assert_eq!(gen.resume(), GeneratorResponse::Yielding(1));
+  assert_eq!(gen.resume(), GeneratorResponse::Done("liftoff"));
+  let (_, resp) = countdown(0);
+  assert_eq!(resp, GeneratorResponse::Done("nothing to count"));
+}
+
+#[test]
+fn return_without_yielding_through_iter() {
+  let mut iter =
+    StartedGenerator::<u32, _>::jumpstart_iter(|_y| async move { "nope" });
+  assert_eq!(iter.next(), None);
+  assert_eq!(iter.consume_response(), Some("nope"));
+
+  let (gen, resp) = StartedGenerator::<u32, _>::run(|_y| async move { 0 });
+  assert_eq!(resp, GeneratorResponse::Done(0));
+  assert!(gen.is_finished());
+  assert_eq!(gen.suspended_at(), None);
+}
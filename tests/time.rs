@@ -0,0 +1,117 @@
+use std::{cell::Cell, rc::Rc};
+
+use dedenne::*;
+
+/// Query with `dt` until it yields `Some`, and say how many frames it took.
+fn frames_until<Y, R: std::fmt::Debug>(
+  gen: &mut StartedGenerator<Option<Y>, R, f32>,
+  dt: f32,
+) -> (u32, Y) {
+  for frame in 1..1000 {
+    match gen.query(dt) {
+      GeneratorResponse::Yielding(Some(y)) => return (frame, y),
+      GeneratorResponse::Yielding(None) => {}
+      GeneratorResponse::Done(done) => panic!("returned {:?}", done),
+    }
+  }
+  panic!("never yielded anything")
+}
+
+#[test]
+fn wait_secs_carries_leftover_time() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let mut late = 0.0;
+    for _ in 0..4 {
+      late = y.wait_secs(0.5).await;
+    }
+    y.ield(Some(late)).await;
+  });
+  // Without carrying the leftover, each wait would take 2 frames
+  assert_eq!(frames_until(&mut gen, 0.375), (6, 0.25));
+}
+
+#[test]
+fn leftover_is_shared_until_forgotten() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let late = y.wait_secs(0.5).await;
+    assert_eq!(y.leftover(), late);
+    // Starts out 0.25 along, so one frame is enough
+    y.wait_secs(0.5).await;
+    y.ield(Some(y.leftover())).await;
+    y.forget_leftover();
+    // Now it needs two whole frames
+    y.wait_secs(1.0).await;
+    y.ield(Some(y.leftover())).await;
+  });
+  assert_eq!(frames_until(&mut gen, 0.75), (2, 0.5));
+  // (The first of these just resumes the `ield`)
+  assert_eq!(frames_until(&mut gen, 0.75), (3, 0.5));
+}
+
+#[test]
+fn wait_secs_returns_overshoot() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let late = y.wait_secs(1.0).await;
+    y.ield(Some(late)).await;
+  });
+  let (frames, late) = frames_until(&mut gen, 0.75);
+  assert_eq!(frames, 2);
+  assert_eq!(late, 0.5);
+}
+
+#[test]
+fn wait_frames_counts_queries() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let took = y.wait_frames(3).await;
+    y.ield(Some(took)).await;
+  });
+  assert_eq!(frames_until(&mut gen, 0.25), (3, 0.75));
+}
+
+#[test]
+fn wait_until_sees_total_time() {
+  let seen = Rc::new(Cell::new(0.0));
+  let seen2 = seen.clone();
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let took = y
+      .wait_until(|total| {
+        seen2.set(total);
+        total >= 1.0
+      })
+      .await;
+    y.ield(Some(took)).await;
+  });
+  assert_eq!(seen.get(), 0.0);
+  assert_eq!(frames_until(&mut gen, 0.5), (2, 1.0));
+}
+
+#[test]
+fn every_catches_up_and_carries() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let mut lateness = Vec::new();
+    let late = y
+      .every(0.25, |late| {
+        lateness.push(late);
+        lateness.len() < 5
+      })
+      .await;
+    y.ield(Some((lateness, late))).await;
+    // The last call was 0.25 late, so this is already done
+    let late = y.wait_secs(0.25).await;
+    y.ield(Some((Vec::new(), late))).await;
+  });
+  // 0.0, 0.5, 1.0, 1.5: fires at 0.5 twice, 1.0 twice, 1.5 once
+  let (frames, (lateness, late)) = frames_until(&mut gen, 0.5);
+  assert_eq!(frames, 3);
+  assert_eq!(lateness, [0.25, 0.0, 0.25, 0.0, 0.25]);
+  assert_eq!(late, 0.25);
+  assert_eq!(frames_until(&mut gen, 0.5), (1, (vec![], 0.0)));
+}
+
+#[test]
+#[should_panic(expected = "every 0 seconds")]
+fn every_rejects_zero() {
+  let _ = StartedGenerator::<(), (), f32>::run(|y| async move {
+    y.every(0.0, |_| true).await;
+  });
+}
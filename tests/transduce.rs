@@ -0,0 +1,52 @@
+use dedenne::*;
+
+#[test]
+fn chunker_flushes() {
+  let chunks = (1..=7)
+    .transduce(|t| async move {
+      let mut chunk = Vec::new();
+      while let Some(x) = t.input().await {
+        chunk.push(x);
+        if chunk.len() == 3 {
+          t.emit(std::mem::take(&mut chunk)).await;
+        }
+      }
+      if !chunk.is_empty() {
+        t.emit(chunk).await;
+      }
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(chunks, vec![vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+}
+
+#[test]
+fn many_per_input() {
+  let mut iter = ["ab", "", "cde"].into_iter().transduce(|t| async move {
+    while let Some(s) = t.input().await {
+      for c in s.chars() {
+        t.emit(c).await;
+      }
+    }
+    t.emit('!').await;
+  });
+  assert_eq!((&mut iter).collect::<String>(), "abcde!".to_string());
+  assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn lazy_and_early_exit() {
+  let mut pulled = 0;
+  let firsts = std::iter::from_fn(|| {
+    pulled += 1;
+    Some(pulled)
+  })
+  .transduce(|t| async move {
+    for _ in 0..2 {
+      let x = t.input().await.unwrap();
+      t.emit(x * 10).await;
+    }
+  })
+  .collect::<Vec<_>>();
+  assert_eq!(firsts, vec![10, 20]);
+  assert_eq!(pulled, 2);
+}
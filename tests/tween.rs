@@ -0,0 +1,134 @@
+use dedenne::{
+  tween::{ease, parallel, repeat, sequence, tween, Animation},
+  GeneratorResponse, StartedGenerator,
+};
+
+/// Query with `dt` until it returns, collecting what it yields.
+fn frames<Y, R>(gen: &mut StartedGenerator<Y, R, f32>, dt: f32) -> Vec<Y> {
+  std::iter::repeat(dt)
+    .map(|dt| gen.query(dt))
+    .map_while(|resp| match resp {
+      GeneratorResponse::Yielding(x) => Some(x),
+      GeneratorResponse::Done(_) => None,
+    })
+    .collect()
+}
+
+#[test]
+fn curves_start_and_end_in_place() {
+  let curves: [fn(f32) -> f32; 19] = [
+    ease::linear,
+    ease::quad_in,
+    ease::quad_out,
+    ease::quad_in_out,
+    ease::cubic_in,
+    ease::cubic_out,
+    ease::cubic_in_out,
+    ease::sine_in,
+    ease::sine_out,
+    ease::sine_in_out,
+    ease::expo_in,
+    ease::expo_out,
+    ease::expo_in_out,
+    ease::back_in,
+    ease::back_out,
+    ease::back_in_out,
+    ease::elastic_in,
+    ease::elastic_out,
+    ease::bounce_out,
+  ];
+  for (i, curve) in curves.into_iter().enumerate() {
+    assert!(curve(0.0).abs() < 1e-3, "curve {} at 0: {}", i, curve(0.0));
+    assert!((curve(1.0) - 1.0).abs() < 1e-3, "curve {} at 1", i);
+  }
+  for curve in [
+    ease::quad_in_out,
+    ease::cubic_in_out,
+    ease::sine_in_out,
+    ease::expo_in_out,
+    ease::back_in_out,
+  ] {
+    assert!((curve(0.5) - 0.5).abs() < 1e-6);
+  }
+  assert!(ease::back_out(0.7) > 1.0);
+  assert_eq!(ease::bounce_in(1.0), 1.0);
+}
+
+#[test]
+fn composing_animations() {
+  let there = tween(0.0f32, 1.0, 1.0, ease::linear);
+  let back = tween(1.0f32, 0.0, 1.0, ease::linear);
+  let both = sequence(there.clone(), back);
+  assert_eq!(both.duration(), 2.0);
+  assert_eq!(both.sample(0.5), 0.5);
+  assert_eq!(both.sample(1.5), 0.5);
+  assert_eq!(both.sample(2.0), 0.0);
+
+  let thrice = repeat(both, 3);
+  assert_eq!(thrice.duration(), 6.0);
+  assert_eq!(thrice.sample(4.25), 0.25);
+  assert_eq!(thrice.sample(6.0), 0.0);
+
+  let side_by_side =
+    parallel(there, tween([0.0f32, 10.0], [4.0, 6.0], 2.0, ease::linear));
+  assert_eq!(side_by_side.duration(), 2.0);
+  assert_eq!(side_by_side.sample(1.5), (1.0, [3.0, 7.0]));
+}
+
+#[test]
+fn tweens_carry_leftover_time() {
+  let (mut gen, first) = StartedGenerator::run(|y| async move {
+    let a = y.tween(0.0f32, 3.0, 1.0, ease::linear).await;
+    let b = y.tween(10.0, 13.0, 2.0, ease::linear).await;
+    y.ield(a).await;
+    y.ield(b).await;
+  });
+  assert_eq!(first, GeneratorResponse::Yielding(0.0));
+  // 0.75 a frame: the first tween ends 0.5s late, and then the frame that
+  // shows its end takes another 0.75, so the second starts 1.25s in
+  assert_eq!(
+    frames(&mut gen, 0.75),
+    [2.25, 3.0, 11.875, 13.0, 1.25, 0.75]
+  );
+}
+
+#[test]
+fn ends_on_the_end_value() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    // Borrowing locals is fine
+    let steps = 4.0;
+    let stepped = |t: f32| (t * steps).floor() / steps;
+    y.tween(0.0f32, 1.0, 1.0, stepped).await;
+  });
+  // Overshoots the end, but still finishes on it
+  assert_eq!(frames(&mut gen, 0.3), [0.25, 0.5, 0.75, 1.0]);
+}
+
+#[test]
+fn play_a_whole_animation() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    let blink = sequence(
+      tween(0.0f32, 1.0, 0.5, ease::linear),
+      tween(1.0, 0.0, 0.5, ease::linear),
+    );
+    let late = y.play(repeat(blink, 2)).await;
+    y.ield(-late).await;
+  });
+  assert_eq!(
+    frames(&mut gen, 0.25),
+    [0.5, 1.0, 0.5, 0.0, 0.5, 1.0, 0.5, 0.0, -0.25]
+  );
+}
+
+#[test]
+fn tweens_and_waits_share_leftover_time() {
+  let (mut gen, _) = StartedGenerator::run(|y| async move {
+    y.wait_secs(0.5).await;
+    // Starts 0.25 in, and finishes 0.75 past the end, which is already
+    // enough for the next wait
+    y.tween(0.0f32, 1.0, 1.0, ease::linear).await;
+    let late = y.wait_secs(0.5).await;
+    y.ield(-late).await;
+  });
+  assert_eq!(frames(&mut gen, 0.75), [0.25, 1.0, -0.25]);
+}
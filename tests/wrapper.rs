@@ -1,4 +1,4 @@
-use dedenne::{Generator, GeneratorResponse};
+use dedenne::{wrapper, Generator, GeneratorResponse, StartedGenerator};
 
 #[test]
 fn wrapping() {
@@ -28,3 +28,114 @@ fn wrapping() {
 
   assert!(matches!(gen.query(0), GeneratorResponse::Done("All done!")));
 }
+
+fn countdown() -> wrapper::BoxedGenerator<u32, u32, &'static str> {
+  Generator::new(Box::new(|y, start| {
+    Box::pin(async move {
+      for x in (0..start).rev() {
+        y.ield(x).await;
+      }
+      "Liftoff!"
+    }) as _
+  }))
+}
+
+#[test]
+fn iterating() {
+  let mut gen = countdown();
+  assert!(!gen.has_started());
+  assert_eq!(gen.start(4), GeneratorResponse::Yielding(3));
+  let mut iter = gen.iter();
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![2, 1, 0]);
+  assert_eq!(iter.consume_response(), Some("Liftoff!"));
+
+  let mut gen = countdown();
+  gen.start(2);
+  assert_eq!(gen.iter().collect::<Vec<_>>(), vec![0]);
+
+  let mut iter = countdown().jumpstart_iter(3);
+  assert_eq!((&mut iter).collect::<Vec<_>>(), vec![2, 1, 0]);
+  assert_eq!(iter.consume_response(), Some("Liftoff!"));
+}
+
+#[test]
+fn into_iter_starts_it() {
+  let make = || {
+    Generator::new(|y, ()| async move {
+      for i in 0..3 {
+        y.ield(i).await;
+      }
+    })
+  };
+  let gen = make();
+  assert!(!gen.has_started());
+  assert_eq!(gen.into_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+  // Already started, so the first yield's been had
+  let mut gen = make();
+  assert_eq!(gen.start(()), GeneratorResponse::Yielding(0));
+  assert_eq!(gen.into_iter().collect::<Vec<_>>(), vec![1, 2]);
+
+  let (started, _) = StartedGenerator::run(|y| async move {
+    y.ield('a').await;
+    y.ield('b').await;
+  });
+  assert_eq!(started.into_iter().collect::<String>(), "b");
+}
+
+#[test]
+fn last_response_and_finished() {
+  let mut gen = countdown();
+  assert_eq!(gen.last_response(), None);
+  assert_eq!(gen.start_ref(1), &GeneratorResponse::Yielding(0));
+  assert_eq!(gen.last_response(), Some(&GeneratorResponse::Yielding(0)));
+  assert!(!gen.is_finished());
+
+  assert_eq!(gen.resume_ref(), &GeneratorResponse::Done("Liftoff!"));
+  assert!(gen.is_finished());
+  assert_eq!(
+    gen.take_last_response(),
+    Some(GeneratorResponse::Done("Liftoff!"))
+  );
+  assert_eq!(gen.last_response(), None);
+  assert!(format!("{:?}", gen).contains("Finished"));
+}
+
+#[test]
+fn poisoned_on_start() {
+  let mut gen = Generator::new(|y, start: bool| async move {
+    if start {
+      panic!("Oh no!");
+    }
+    y.ield(()).await;
+  });
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    gen.start(true);
+  }));
+  assert!(result.is_err());
+  assert!(gen.is_poisoned());
+  assert!(format!("{:?}", gen).contains("Poisoned"));
+
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    gen.resume();
+  }));
+  let msg = result.unwrap_err();
+  let msg = msg.downcast_ref::<&str>().unwrap();
+  assert!(msg.contains("panicked while starting"));
+}
+
+#[test]
+#[should_panic(expected = "Tried to query a generator after it panicked")]
+fn poisoned_on_query() {
+  let mut gen = Generator::new(|y, ()| async move {
+    y.ield(()).await;
+    panic!("Oh no!");
+  });
+  gen.start(());
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    gen.resume();
+  }));
+  assert!(result.is_err());
+  assert!(gen.is_poisoned());
+  gen.resume();
+}